@@ -7,5 +7,11 @@ pub mod v9_1;
 #[cfg(feature = "v10_0")]
 pub mod v10_0;
 
+#[cfg(feature = "v11_0")]
+pub mod v11_0;
+
 #[cfg(feature = "v11_1")]
 pub mod v11_1;
+
+#[cfg(feature = "v12_0")]
+pub mod v12_0;
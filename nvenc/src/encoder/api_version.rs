@@ -0,0 +1,279 @@
+/// Struct version constants (the `*_VER` macros) of a single NvEnc API version. They encode the
+/// API version so a struct has to be tagged with the constant of the version the session was
+/// opened with.
+pub struct StructVersions {
+    pub caps_param: u32,
+    pub create_input_buffer: u32,
+    pub create_bitstream_buffer: u32,
+    pub config: u32,
+    pub initialize_params: u32,
+    pub reconfigure_params: u32,
+    pub preset_config: u32,
+    pub pic_params: u32,
+    pub lock_bitstream: u32,
+    pub lock_input_buffer: u32,
+    pub map_input_resource: u32,
+    pub register_resource: u32,
+    pub sequence_param_payload: u32,
+    // Completion events are only registered on Windows
+    #[cfg_attr(not(windows), allow(dead_code))]
+    pub event_params: u32,
+    pub open_encode_session_ex_params: u32,
+    pub function_list: u32,
+}
+
+macro_rules! struct_versions {
+    ($version:ident) => {
+        StructVersions {
+            caps_param: nvenc_sys::$version::NV_ENC_CAPS_PARAM_VER,
+            create_input_buffer: nvenc_sys::$version::NV_ENC_CREATE_INPUT_BUFFER_VER,
+            create_bitstream_buffer: nvenc_sys::$version::NV_ENC_CREATE_BITSTREAM_BUFFER_VER,
+            config: nvenc_sys::$version::NV_ENC_CONFIG_VER,
+            initialize_params: nvenc_sys::$version::NV_ENC_INITIALIZE_PARAMS_VER,
+            reconfigure_params: nvenc_sys::$version::NV_ENC_RECONFIGURE_PARAMS_VER,
+            preset_config: nvenc_sys::$version::NV_ENC_PRESET_CONFIG_VER,
+            pic_params: nvenc_sys::$version::NV_ENC_PIC_PARAMS_VER,
+            lock_bitstream: nvenc_sys::$version::NV_ENC_LOCK_BITSTREAM_VER,
+            lock_input_buffer: nvenc_sys::$version::NV_ENC_LOCK_INPUT_BUFFER_VER,
+            map_input_resource: nvenc_sys::$version::NV_ENC_MAP_INPUT_RESOURCE_VER,
+            register_resource: nvenc_sys::$version::NV_ENC_REGISTER_RESOURCE_VER,
+            sequence_param_payload: nvenc_sys::$version::NV_ENC_SEQUENCE_PARAM_PAYLOAD_VER,
+            event_params: nvenc_sys::$version::NV_ENC_EVENT_PARAMS_VER,
+            open_encode_session_ex_params:
+                nvenc_sys::$version::NV_ENC_OPEN_ENCODE_SESSION_EX_PARAMS_VER,
+            function_list: nvenc_sys::$version::NV_ENCODE_API_FUNCTION_LIST_VER,
+        }
+    };
+}
+
+static V9_0_STRUCT_VERSIONS: StructVersions = struct_versions!(v9_0);
+static V9_1_STRUCT_VERSIONS: StructVersions = struct_versions!(v9_1);
+static V10_0_STRUCT_VERSIONS: StructVersions = struct_versions!(v10_0);
+static V11_0_STRUCT_VERSIONS: StructVersions = struct_versions!(v11_0);
+static V11_1_STRUCT_VERSIONS: StructVersions = struct_versions!(v11_1);
+#[cfg(feature = "v12_0")]
+static V12_0_STRUCT_VERSIONS: StructVersions = struct_versions!(v12_0);
+
+/// Fails to compile if a struct in `crate::sys` does not have the layout of the same struct in
+/// an older version, comparing the size and the offsets of the fields that both versions have.
+/// Bitfields are not compared.
+macro_rules! assert_same_layout {
+    ($version:ident, $name:ident { $($field:ident),* $(,)? }) => {
+        const _: () = {
+            use std::mem::{offset_of, size_of};
+            assert!(size_of::<crate::sys::$name>() == size_of::<nvenc_sys::$version::$name>());
+            $(assert!(
+                offset_of!(crate::sys::$name, $field)
+                    == offset_of!(nvenc_sys::$version::$name, $field)
+            );)*
+        };
+    };
+}
+
+/// The structs that are passed to the driver with the layout of the newest version, see
+/// `ApiVersion`.
+macro_rules! assert_same_layouts {
+    ($($version:ident),*) => {$(
+        assert_same_layout!($version, NV_ENC_CONFIG {
+            version,
+            profileGUID,
+            gopLength,
+            frameIntervalP,
+            monoChromeEncoding,
+            frameFieldMode,
+            mvPrecision,
+            rcParams,
+            encodeCodecConfig,
+            reserved,
+            reserved2,
+        });
+        assert_same_layout!($version, NV_ENC_INITIALIZE_PARAMS {
+            version,
+            encodeGUID,
+            presetGUID,
+            encodeWidth,
+            encodeHeight,
+            darWidth,
+            darHeight,
+            frameRateNum,
+            frameRateDen,
+            enableEncodeAsync,
+            enablePTD,
+            privDataSize,
+            privData,
+            encodeConfig,
+            maxEncodeWidth,
+            maxEncodeHeight,
+            maxMEHintCountsPerBlock,
+            reserved2,
+        });
+        assert_same_layout!($version, NV_ENC_LOCK_BITSTREAM {
+            version,
+            outputBitstream,
+            sliceOffsets,
+            frameIdx,
+            hwEncodeStatus,
+            numSlices,
+            bitstreamSizeInBytes,
+            outputTimeStamp,
+            outputDuration,
+            bitstreamBufferPtr,
+            pictureType,
+            pictureStruct,
+            frameAvgQP,
+            frameSatd,
+            ltrFrameIdx,
+            ltrFrameBitmap,
+            intraMBCount,
+            interMBCount,
+            averageMVX,
+            averageMVY,
+            reserved2,
+        });
+        assert_same_layout!($version, NV_ENC_RC_PARAMS {
+            version,
+            rateControlMode,
+            constQP,
+            averageBitRate,
+            maxBitRate,
+            vbvBufferSize,
+            vbvInitialDelay,
+            minQP,
+            maxQP,
+            initialRCQP,
+            temporallayerIdxMask,
+            temporalLayerQP,
+            targetQuality,
+            targetQualityLSB,
+            lookaheadDepth,
+            qpMapMode,
+        });
+    )*};
+}
+
+assert_same_layouts!(v9_0, v9_1, v10_0, v11_0, v11_1);
+
+/// An NvEnc API version that the crate is compiled against.
+///
+/// The structs in `crate::sys` come from the newest version. NVIDIA only ever adds fields by
+/// taking space from the reserved arrays so they have the same layout as the structs of older
+/// versions, as long as the fields that the older version does not know about are left zeroed.
+/// This is checked at compile time for the structs whose layout the crate relies on. The one
+/// exception is `NV_ENC_PIC_PARAMS` in 12.0 which is handled by
+/// `RawEncoder::encode_picture`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ApiVersion {
+    V9_0,
+    V9_1,
+    V10_0,
+    V11_0,
+    V11_1,
+    #[cfg(feature = "v12_0")]
+    V12_0,
+}
+
+impl ApiVersion {
+    /// Every compiled version, newest first.
+    const ALL: &'static [ApiVersion] = &[
+        #[cfg(feature = "v12_0")]
+        ApiVersion::V12_0,
+        ApiVersion::V11_1,
+        ApiVersion::V11_0,
+        ApiVersion::V10_0,
+        ApiVersion::V9_1,
+        ApiVersion::V9_0,
+    ];
+
+    /// Picks the newest compiled version that is not newer than `max_supported_version`, the
+    /// value returned by `NvEncodeAPIGetMaxSupportedVersion`. Returns `None` if the driver is
+    /// older than all of them.
+    pub fn newest_supported(max_supported_version: u32) -> Option<Self> {
+        ApiVersion::ALL
+            .iter()
+            .copied()
+            .find(|version| (version.major() << 4 | version.minor()) <= max_supported_version)
+    }
+
+    pub fn major(self) -> u32 {
+        match self {
+            ApiVersion::V9_0 | ApiVersion::V9_1 => 9,
+            ApiVersion::V10_0 => 10,
+            ApiVersion::V11_0 | ApiVersion::V11_1 => 11,
+            #[cfg(feature = "v12_0")]
+            ApiVersion::V12_0 => 12,
+        }
+    }
+
+    pub fn minor(self) -> u32 {
+        match self {
+            ApiVersion::V9_1 | ApiVersion::V11_1 => 1,
+            _ => 0,
+        }
+    }
+
+    /// The value of `NVENCAPI_VERSION` for this version.
+    pub fn nvencapi_version(self) -> u32 {
+        self.major() | self.minor() << 24
+    }
+
+    /// AV1 needs the structs from 12.0 so it is only available with the `v12_0` feature.
+    pub fn supports_av1(self) -> bool {
+        cfg!(feature = "v12_0") && self.major() >= 12
+    }
+
+    pub fn struct_versions(self) -> &'static StructVersions {
+        match self {
+            ApiVersion::V9_0 => &V9_0_STRUCT_VERSIONS,
+            ApiVersion::V9_1 => &V9_1_STRUCT_VERSIONS,
+            ApiVersion::V10_0 => &V10_0_STRUCT_VERSIONS,
+            ApiVersion::V11_0 => &V11_0_STRUCT_VERSIONS,
+            ApiVersion::V11_1 => &V11_1_STRUCT_VERSIONS,
+            #[cfg(feature = "v12_0")]
+            ApiVersion::V12_0 => &V12_0_STRUCT_VERSIONS,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiation() {
+        assert_eq!(ApiVersion::newest_supported(8 << 4 | 2), None);
+        assert_eq!(ApiVersion::newest_supported(9 << 4), Some(ApiVersion::V9_0));
+        assert_eq!(
+            ApiVersion::newest_supported(10 << 4 | 2),
+            Some(ApiVersion::V10_0)
+        );
+        assert_eq!(
+            ApiVersion::newest_supported(11 << 4 | 1),
+            Some(ApiVersion::V11_1)
+        );
+        assert_eq!(
+            ApiVersion::newest_supported(u32::MAX),
+            ApiVersion::ALL.first().copied()
+        );
+    }
+
+    #[test]
+    fn matches_sys_constants() {
+        for &version in ApiVersion::ALL {
+            let expected = match version {
+                ApiVersion::V9_0 => nvenc_sys::v9_0::NVENCAPI_VERSION,
+                ApiVersion::V9_1 => nvenc_sys::v9_1::NVENCAPI_VERSION,
+                ApiVersion::V10_0 => nvenc_sys::v10_0::NVENCAPI_VERSION,
+                ApiVersion::V11_0 => nvenc_sys::v11_0::NVENCAPI_VERSION,
+                ApiVersion::V11_1 => nvenc_sys::v11_1::NVENCAPI_VERSION,
+                #[cfg(feature = "v12_0")]
+                ApiVersion::V12_0 => nvenc_sys::v12_0::NVENCAPI_VERSION,
+            };
+            assert_eq!(version.nvencapi_version(), expected);
+            // Bits 16-23 hold the version of the struct itself and bits 28-30 are always set
+            assert_eq!(
+                version.struct_versions().function_list & 0x0f00_00ff,
+                expected
+            );
+        }
+    }
+}
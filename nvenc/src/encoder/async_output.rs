@@ -0,0 +1,292 @@
+use super::{encoder_output::EncoderOutput, packet::OwnedEncodedPacket, stats::SessionStats};
+use crate::{NvEncError, Result};
+use std::{
+    collections::VecDeque,
+    future::poll_fn,
+    pin::Pin,
+    sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError},
+    task::{Context, Poll, Waker},
+};
+
+struct State {
+    packets: VecDeque<Result<OwnedEncodedPacket>>,
+    /// Set by the reading thread once it has stopped.
+    finished: bool,
+    /// Set when the `AsyncEncoderOutput` is dropped.
+    closed: bool,
+    waker: Option<Waker>,
+    session_stats: SessionStats,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    /// Signaled when a packet has been taken or the `AsyncEncoderOutput` is dropped.
+    space_available: Condvar,
+    capacity: usize,
+}
+
+impl Shared {
+    fn new(capacity: usize) -> Self {
+        Shared {
+            state: Mutex::new(State {
+                packets: VecDeque::with_capacity(capacity),
+                finished: false,
+                closed: false,
+                waker: None,
+                session_stats: SessionStats::default(),
+            }),
+            space_available: Condvar::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    // Nothing panics while holding the lock, wakers are only woken after releasing it
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Blocks the reading thread while the buffer is full. Returns `false` once the
+    /// `AsyncEncoderOutput` has been dropped.
+    fn wait_for_space(&self) -> bool {
+        let mut state = self.lock();
+        while state.packets.len() >= self.capacity && !state.closed {
+            state = self
+                .space_available
+                .wait(state)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        !state.closed
+    }
+
+    /// Buffers a packet read by the thread and wakes the task waiting for it.
+    fn push(&self, result: Result<OwnedEncodedPacket>, session_stats: SessionStats) {
+        let mut state = self.lock();
+        state.packets.push_back(result);
+        state.session_stats = session_stats;
+        let waker = state.waker.take();
+        drop(state);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Marks the end of the packets and wakes the task waiting for more.
+    fn finish(&self) {
+        let mut state = self.lock();
+        state.finished = true;
+        let waker = state.waker.take();
+        drop(state);
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+/// An `EncoderOutput` whose packets are awaited instead of blocked on, created with
+/// `EncoderOutput::into_async`. Requires the `async` feature.
+///
+/// A dedicated thread waits for the output and buffers the packets until they are taken with
+/// `next_packet` or through the `Stream` implementation, which ends after the end of the stream
+/// or after the first error.
+pub struct AsyncEncoderOutput {
+    shared: Arc<Shared>,
+}
+
+impl EncoderOutput {
+    /// Moves the output to a dedicated thread that waits for packets, so they can be awaited.
+    ///
+    /// At most `capacity` packets, but at least one, are buffered. Once that many have not been
+    /// taken the thread stops reading, so `EncoderInput` blocks once all of its buffers are in use.
+    /// Returns `ThreadSpawnFailed` if the thread cannot be started.
+    pub fn into_async(self, capacity: usize) -> Result<AsyncEncoderOutput> {
+        let shared = Arc::new(Shared::new(capacity));
+
+        let thread_shared = shared.clone();
+        std::thread::Builder::new()
+            .name("nvenc-output".to_owned())
+            .spawn(move || read_packets(self, thread_shared))
+            .map_err(|_| NvEncError::ThreadSpawnFailed)?;
+
+        Ok(AsyncEncoderOutput { shared })
+    }
+}
+
+/// Body of the reading thread. Stops after the end of the stream or an error, since the output
+/// cannot be read past a frame whose bitstream could not be locked.
+fn read_packets(output: EncoderOutput, shared: Arc<Shared>) {
+    // Also wakes the task if the thread panics, so it does not wait forever
+    struct Finish<'a>(&'a Shared);
+    impl Drop for Finish<'_> {
+        fn drop(&mut self) {
+            self.0.finish();
+        }
+    }
+    let _finish = Finish(&shared);
+
+    while shared.wait_for_space() {
+        let mut packet = OwnedEncodedPacket::default();
+        match output.wait_for_owned_packet(&mut packet) {
+            Ok(()) => shared.push(Ok(packet), output.session_stats()),
+            Err(NvEncError::EndOfStream) => return,
+            Err(err) => {
+                shared.push(Err(err), output.session_stats());
+                return;
+            }
+        }
+    }
+}
+
+impl AsyncEncoderOutput {
+    /// Waits for the next packet. Returns `NvEncError::EndOfStream` once every frame has been
+    /// read or after an error.
+    ///
+    /// Cancellation safe: a packet is only taken from the buffer when the future completes.
+    pub async fn next_packet(&mut self) -> Result<OwnedEncodedPacket> {
+        poll_fn(|cx| self.poll_next_packet(cx)).await
+    }
+
+    /// Polling version of `next_packet`.
+    pub fn poll_next_packet(&mut self, cx: &mut Context<'_>) -> Poll<Result<OwnedEncodedPacket>> {
+        let mut state = self.shared.lock();
+        if let Some(result) = state.packets.pop_front() {
+            drop(state);
+            self.shared.space_available.notify_one();
+            return Poll::Ready(result);
+        }
+        if state.finished {
+            return Poll::Ready(Err(NvEncError::EndOfStream));
+        }
+
+        match &mut state.waker {
+            Some(waker) if waker.will_wake(cx.waker()) => {}
+            waker => *waker = Some(cx.waker().clone()),
+        }
+        Poll::Pending
+    }
+
+    /// `EncoderOutput::session_stats` as of the last packet that was read by the thread.
+    pub fn session_stats(&self) -> SessionStats {
+        self.shared.lock().session_stats
+    }
+}
+
+impl futures_core::Stream for AsyncEncoderOutput {
+    type Item = Result<OwnedEncodedPacket>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.get_mut()
+            .poll_next_packet(cx)
+            .map(|result| match result {
+                Err(NvEncError::EndOfStream) => None,
+                result => Some(result),
+            })
+    }
+}
+
+impl Drop for AsyncEncoderOutput {
+    fn drop(&mut self) {
+        // The thread stops once the packet it is waiting for, if any, has been read
+        self.shared.lock().closed = true;
+        self.shared.space_available.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        task::Wake,
+        time::Duration,
+    };
+
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn packet(timestamp: u64) -> Result<OwnedEncodedPacket> {
+        Ok(OwnedEncodedPacket {
+            timestamp,
+            ..OwnedEncodedPacket::default()
+        })
+    }
+
+    fn buffered(shared: &Shared) -> usize {
+        shared.lock().packets.len()
+    }
+
+    #[test]
+    fn back_pressure_and_end_of_stream() {
+        let mut output = AsyncEncoderOutput {
+            shared: Arc::new(Shared::new(2)),
+        };
+        let wakes = Arc::new(CountingWaker(AtomicUsize::new(0)));
+        let waker = Waker::from(wakes.clone());
+        let mut cx = Context::from_waker(&waker);
+
+        assert!(output.poll_next_packet(&mut cx).is_pending());
+
+        let shared = output.shared.clone();
+        let reader = std::thread::spawn(move || {
+            for timestamp in 0..5 {
+                assert!(shared.wait_for_space());
+                shared.push(packet(timestamp), SessionStats::default());
+            }
+            shared.finish();
+        });
+
+        // The thread stops reading once the buffer is full
+        while buffered(&output.shared) < 2 {
+            std::thread::yield_now();
+        }
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(buffered(&output.shared), 2);
+        assert!(wakes.0.load(Ordering::Relaxed) >= 1);
+
+        for timestamp in 0..5 {
+            let packet = loop {
+                if let Poll::Ready(result) = output.poll_next_packet(&mut cx) {
+                    break result.unwrap();
+                }
+                std::thread::yield_now();
+            };
+            assert_eq!(packet.timestamp, timestamp);
+        }
+        reader.join().unwrap();
+
+        assert!(matches!(
+            output.poll_next_packet(&mut cx),
+            Poll::Ready(Err(NvEncError::EndOfStream))
+        ));
+        let stream = Pin::new(&mut output);
+        assert!(matches!(
+            futures_core::Stream::poll_next(stream, &mut cx),
+            Poll::Ready(None)
+        ));
+    }
+
+    #[test]
+    fn drop_while_blocked() {
+        let output = AsyncEncoderOutput {
+            shared: Arc::new(Shared::new(1)),
+        };
+
+        let shared = output.shared.clone();
+        let reader = std::thread::spawn(move || {
+            assert!(shared.wait_for_space());
+            shared.push(packet(0), SessionStats::default());
+            // Blocks until the output is dropped
+            shared.wait_for_space()
+        });
+
+        while buffered(&output.shared) < 1 {
+            std::thread::yield_now();
+        }
+        drop(output);
+        assert!(!reader.join().unwrap());
+    }
+}
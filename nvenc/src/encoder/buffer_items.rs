@@ -1,21 +1,38 @@
 use super::{
     event::{EventObject, EventObjectTrait},
     raw_encoder::RawEncoder,
-    texture::TextureImplTrait,
+    texture::{IntoNvEncBufferFormat, TextureImplTrait},
 };
-use crate::Result;
+use crate::{BufferFormat, Result};
 use std::{
+    cell::UnsafeCell,
     mem::{ManuallyDrop, MaybeUninit},
     os::raw::c_void,
     ptr::NonNull,
 };
 
+/// The resource that input frames are copied into.
+pub enum InputResource {
+    /// A texture registered with `NvEncRegisterResource` that needs to be mapped before encoding.
+    Registered(NonNull<c_void>),
+    /// A buffer allocated with `NvEncCreateInputBuffer` that is filled from host memory.
+    HostBuffer(NonNull<c_void>),
+}
+
 pub struct EncoderBufferItems {
-    pub registered_resource: NonNull<c_void>,
+    pub input_resource: InputResource,
     pub mapped_input: crate::sys::NV_ENC_INPUT_PTR,
     pub output_buffer: NonNull<c_void>,
-    pub event_obj: EventObject,
+    /// Signaled when the output is ready. `None` in synchronous mode where
+    /// `NvEncLockBitstream` blocks instead.
+    pub event_obj: Option<EventObject>,
     pub end_of_stream: bool,
+    /// Receives the slice offsets when the output is locked. Empty if they are not reported.
+    /// Only the reader touches it, while it has exclusive access to the item.
+    pub slice_offsets: UnsafeCell<Vec<u32>>,
+    /// Data from `EncodeFrameOptions::user_data` that is handed back with the output. Set by the
+    /// writer and taken by the reader.
+    pub user_data: UnsafeCell<Option<Vec<u8>>>,
 }
 
 // SAFETY: All of the struct members are pointers or pointer-like objects (`HANDLE` for the Event)
@@ -28,47 +45,121 @@ impl EncoderBufferItems {
         raw_encoder: &RawEncoder,
         texture: &T,
         pitch_or_subresource_index: u32,
+        async_encode: bool,
     ) -> Result<Self>
     where
         T: TextureImplTrait,
     {
         let registered_resource =
             register_input_resource(raw_encoder, texture, pitch_or_subresource_index)?;
+        let items = EncoderBufferItems::with_input_resource(
+            raw_encoder,
+            InputResource::Registered(registered_resource.registered_resource),
+            async_encode,
+        )?;
+
+        // All calls succeeded, remove the RAII wrapper
+        let _ = ManuallyDrop::new(registered_resource);
+        Ok(items)
+    }
+
+    /// Create the items with an input buffer that is filled from host memory instead of a
+    /// registered texture.
+    pub fn with_host_buffer(
+        raw_encoder: &RawEncoder,
+        width: u32,
+        height: u32,
+        format: BufferFormat,
+        async_encode: bool,
+    ) -> Result<Self> {
+        let input_buffer = create_input_buffer(raw_encoder, width, height, format)?;
+        let items = EncoderBufferItems::with_input_resource(
+            raw_encoder,
+            InputResource::HostBuffer(input_buffer.input_buffer),
+            async_encode,
+        )?;
+
+        // All calls succeeded, remove the RAII wrapper
+        let _ = ManuallyDrop::new(input_buffer);
+        Ok(items)
+    }
+
+    fn with_input_resource(
+        raw_encoder: &RawEncoder,
+        input_resource: InputResource,
+        async_encode: bool,
+    ) -> Result<Self> {
         let output_buffer = create_output_buffer(raw_encoder)?;
 
-        let event_obj = EventObject::new()?;
-        let registered_async = register_async_event(raw_encoder, &event_obj)?;
+        let event_obj = if async_encode {
+            let event_obj = EventObject::new()?;
+            let registered_async = register_async_event(raw_encoder, &event_obj)?;
+            let _ = ManuallyDrop::new(registered_async);
+            Some(event_obj)
+        } else {
+            None
+        };
 
         // All calls succeeded, remove the RAII wrappers
 
-        let registered_resource = {
-            let registered_resource = ManuallyDrop::new(registered_resource);
-            registered_resource.registered_resource
-        };
         let output_buffer = {
             let output_buffer = ManuallyDrop::new(output_buffer);
             output_buffer.output_buffer
         };
-        let _ = ManuallyDrop::new(registered_async);
 
         Ok(EncoderBufferItems {
-            registered_resource,
+            input_resource,
             mapped_input: std::ptr::null_mut(),
             output_buffer,
             event_obj,
             end_of_stream: false,
+            slice_offsets: UnsafeCell::new(Vec::new()),
+            user_data: UnsafeCell::new(None),
         })
     }
 
+    /// Make room for `max_slices` slice offsets.
+    pub fn with_slice_offsets(mut self, max_slices: usize) -> Self {
+        self.slice_offsets = UnsafeCell::new(vec![0; max_slices]);
+        self
+    }
+
+    /// Releases the input after the frame has been encoded. Only registered resources are mapped
+    /// so this does nothing for host buffers.
+    pub fn unmap_input(&self, raw_encoder: &RawEncoder) -> Result<()> {
+        match self.input_resource {
+            InputResource::Registered(_) => unsafe {
+                raw_encoder.unmap_input_resource(self.mapped_input)
+            },
+            InputResource::HostBuffer(_) => Ok(()),
+        }
+    }
+
+    /// The event to pass to `NvEncEncodePicture`. Null in synchronous mode.
+    pub fn completion_event(&self) -> *mut c_void {
+        self.event_obj
+            .as_ref()
+            .map_or(std::ptr::null_mut(), |event_obj| event_obj.as_ptr())
+    }
+
     pub fn cleanup(&mut self, raw_encoder: &RawEncoder) {
         // TODO: Prob should log the errors instead of ignoring them.
         unsafe {
-            let _ = raw_encoder.unmap_input_resource(self.mapped_input);
-            let _ = raw_encoder.unregister_resource(self.registered_resource.as_ptr());
+            match self.input_resource {
+                InputResource::Registered(registered_resource) => {
+                    let _ = raw_encoder.unmap_input_resource(self.mapped_input);
+                    let _ = raw_encoder.unregister_resource(registered_resource.as_ptr());
+                }
+                InputResource::HostBuffer(input_buffer) => {
+                    let _ = raw_encoder.destroy_input_buffer(input_buffer.as_ptr());
+                }
+            }
             let _ = raw_encoder.unlock_bitstream(self.output_buffer.as_ptr());
             let _ = raw_encoder.destroy_bitstream_buffer(self.output_buffer.as_ptr());
         }
-        let _ignore = unregister_async_event(raw_encoder, &self.event_obj);
+        if let Some(event_obj) = &self.event_obj {
+            let _ignore = unregister_async_event(raw_encoder, event_obj);
+        }
     }
 }
 
@@ -98,6 +189,7 @@ where
 {
     let mut register_resource_args =
         texture.build_register_resource_args(pitch_or_subresource_index)?;
+    register_resource_args.version = raw_encoder.struct_versions().register_resource;
 
     unsafe {
         raw_encoder.register_resource(&mut register_resource_args)?;
@@ -112,6 +204,48 @@ where
     })
 }
 
+struct InputBufferRAII<'a> {
+    input_buffer: NonNull<c_void>,
+    raw_encoder: &'a RawEncoder,
+}
+
+impl<'a> Drop for InputBufferRAII<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = self
+                .raw_encoder
+                .destroy_input_buffer(self.input_buffer.as_ptr());
+        }
+    }
+}
+
+/// Allocate an input buffer for frames in host memory. Should be called only after the encoder has
+/// been configured.
+fn create_input_buffer<'a>(
+    raw_encoder: &'a RawEncoder,
+    width: u32,
+    height: u32,
+    format: BufferFormat,
+) -> Result<InputBufferRAII<'a>> {
+    let mut create_input_buffer_params: crate::sys::NV_ENC_CREATE_INPUT_BUFFER =
+        unsafe { MaybeUninit::zeroed().assume_init() };
+    create_input_buffer_params.version = raw_encoder.struct_versions().create_input_buffer;
+    create_input_buffer_params.width = width;
+    create_input_buffer_params.height = height;
+    create_input_buffer_params.bufferFmt = format.into_nvenc_buffer_format();
+
+    unsafe {
+        raw_encoder.create_input_buffer(&mut create_input_buffer_params)?;
+    }
+
+    // Should not fail since `nvEncCreateInputBuffer` succeeded
+    let input_buffer = NonNull::new(create_input_buffer_params.inputBuffer).unwrap();
+    Ok(InputBufferRAII {
+        input_buffer,
+        raw_encoder,
+    })
+}
+
 struct OutputBufferRAII<'a> {
     output_buffer: NonNull<c_void>,
     raw_encoder: &'a RawEncoder,
@@ -131,7 +265,7 @@ impl<'a> Drop for OutputBufferRAII<'a> {
 fn create_output_buffer<'a>(raw_encoder: &'a RawEncoder) -> Result<OutputBufferRAII<'a>> {
     let mut create_bitstream_buffer_params: crate::sys::NV_ENC_CREATE_BITSTREAM_BUFFER =
         unsafe { MaybeUninit::zeroed().assume_init() };
-    create_bitstream_buffer_params.version = crate::sys::NV_ENC_CREATE_BITSTREAM_BUFFER_VER;
+    create_bitstream_buffer_params.version = raw_encoder.struct_versions().create_bitstream_buffer;
 
     unsafe {
         raw_encoder.create_bitstream_buffer(&mut create_bitstream_buffer_params)?;
@@ -163,7 +297,7 @@ fn register_async_event<'a, 'b>(
     #[cfg(windows)]
     unsafe {
         let mut event_params: crate::sys::NV_ENC_EVENT_PARAMS = MaybeUninit::zeroed().assume_init();
-        event_params.version = crate::sys::NV_ENC_EVENT_PARAMS_VER;
+        event_params.version = raw_encoder.struct_versions().event_params;
         event_params.completionEvent = event_obj.as_ptr();
         raw_encoder.register_async_event(&mut event_params)?;
     }
@@ -177,7 +311,7 @@ fn unregister_async_event(raw_encoder: &RawEncoder, event_obj: &EventObject) ->
     #[cfg(windows)]
     unsafe {
         let mut event_params: crate::sys::NV_ENC_EVENT_PARAMS = MaybeUninit::zeroed().assume_init();
-        event_params.version = crate::sys::NV_ENC_EVENT_PARAMS_VER;
+        event_params.version = raw_encoder.struct_versions().event_params;
         event_params.completionEvent = event_obj.as_ptr();
         raw_encoder.unregister_async_event(&mut event_params)?;
     }
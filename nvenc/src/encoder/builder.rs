@@ -1,28 +1,40 @@
 use super::{
-    config::{EncodeParams, ExtraOptions},
+    api_version::ApiVersion,
+    buffer_items::EncoderBufferItems,
+    capabilities::EncoderCapabilities,
+    config::{EncodeParams, ExtraOptions, MAX_LOOKAHEAD_DEPTH},
     device::{DeviceImplTrait, IntoDevice},
-    encoder_input::EncoderInput,
+    encoder_input::{EncoderInput, InputStaging},
     encoder_output::EncoderOutput,
     library::Library,
     raw_encoder::RawEncoder,
     shared::encoder_channel,
-    texture::TextureBufferImplTrait,
+    texture::{IntoNvEncBufferFormat, TextureBufferImplTrait},
+};
+use crate::{
+    BFrameRefMode, BufferFormat, Codec, CodecProfile, ColorDescription, ContentLightLevel,
+    EncodePreset, Level, MasteringDisplay, MultiPassSetting, NvEncError, Qp, QpMapMode,
+    RateControl, Result, SliceMode, TuningInfo, WaitStrategy,
 };
-use crate::{Codec, CodecProfile, EncodePreset, MultiPassSetting, NvEncError, Result, TuningInfo};
 use std::mem::MaybeUninit;
 
-/// Size of the ring buffer that is shared between the input and output
+/// Minimum size of the ring buffer that is shared between the input and output
 pub const BUFFER_SIZE: usize = 8;
 
-/// Checks if the user's NvEncAPI version is supported.
-fn is_version_supported(version: u32) -> bool {
-    // TODO: Change this logic once older versions (9.0 to 10.0) are supported
-    let this_version = crate::sys::NVENCAPI_MAJOR_VERSION << 4 | crate::sys::NVENCAPI_MINOR_VERSION;
-    if version >= this_version {
-        true
-    } else {
-        false
-    }
+/// Slots on top of the frames held by the encoder so that the input does not stall while the
+/// output is being read. Same as `m_nExtraOutputDelay` in NVIDIA's samples.
+const EXTRA_OUTPUT_DELAY: usize = 3;
+
+/// Upper bound of the number of slices in a frame, which is the number of 16x16 macroblocks.
+fn max_slices(width: u32, height: u32) -> usize {
+    (width as usize).div_ceil(16) * (height as usize).div_ceil(16)
+}
+
+/// The encoder does not output anything until it has been given all the frames it holds on to, so
+/// each of them needs its own slot on the ring buffer or the input blocks forever.
+fn buffer_size(encode_params: &EncodeParams) -> usize {
+    let required = 1 + encode_params.frames_held_by_encoder() + EXTRA_OUTPUT_DELAY;
+    required.next_power_of_two().max(BUFFER_SIZE)
 }
 
 /// Builder for an encoder.
@@ -39,6 +51,7 @@ where
     preset: Option<EncodePreset>,
     tuning_info: TuningInfo,
     extra_options: ExtraOptions,
+    wait_strategy: WaitStrategy,
 }
 
 impl<D> EncoderBuilder<D>
@@ -47,16 +60,27 @@ where
 {
     /// Create a new `EncoderBuilder` using the given device.
     pub fn new<I: IntoDevice<Device = D>>(device: I) -> Result<Self> {
-        let library = Library::load()?;
-
-        let max_supported_version = library.get_max_supported_version()?;
+        EncoderBuilder::with_library(device, Library::load()?)
+    }
 
-        if !is_version_supported(max_supported_version) {
-            return Err(NvEncError::UnsupportedVersion);
+    /// Create a new `EncoderBuilder` using the given device, loading `libnvidia-encode.so.1` from
+    /// `path` instead of the driver directories. The file and its parent directories must still be
+    /// owned by root and not be writable by other users.
+    #[cfg(target_os = "linux")]
+    pub fn new_with_library_path<I: IntoDevice<Device = D>>(device: I, path: &str) -> Result<Self> {
+        if !std::path::Path::new(path).is_absolute() {
+            return Err(NvEncError::LibraryLoadingFailed);
         }
+        EncoderBuilder::with_library(device, Library::load_from(path)?)
+    }
+
+    fn with_library<I: IntoDevice<Device = D>>(device: I, library: Library) -> Result<Self> {
+        let max_supported_version = library.get_max_supported_version()?;
+        let api_version = ApiVersion::newest_supported(max_supported_version)
+            .ok_or(NvEncError::UnsupportedVersion)?;
 
         let device = device.into_device();
-        let raw_encoder = RawEncoder::new(&device, library)?;
+        let raw_encoder = RawEncoder::new(&device, library, api_version)?;
 
         Ok(EncoderBuilder {
             device,
@@ -67,11 +91,22 @@ where
             preset: None,
             tuning_info: TuningInfo::Undefined,
             extra_options: ExtraOptions::default(),
+            wait_strategy: WaitStrategy::default(),
         })
     }
 
+    /// The NvEnc API version (major, minor) that was negotiated with the driver. This is the newest
+    /// version that both the driver and this library support.
+    pub fn api_version(&self) -> (u32, u32) {
+        let api_version = self.raw_encoder.api_version();
+        (api_version.major(), api_version.minor())
+    }
+
     /// Set the encoder to use the given `Codec`.
     pub fn with_codec(&mut self, codec: Codec) -> Result<&mut Self> {
+        if codec == Codec::Av1 && !self.raw_encoder.api_version().supports_av1() {
+            return Err(NvEncError::UnsupportedByApiVersion);
+        }
         if self.supported_codecs()?.contains(&codec) {
             self.codec = Some(codec);
             Ok(self)
@@ -106,19 +141,26 @@ where
         }
     }
 
-    /// Set the encoder to use the given `TuningInfo`.
+    /// Set the encoder to use the given `TuningInfo`. Requires NvEnc API 10.0.
     pub fn with_tuning_info(&mut self, tuning_info: TuningInfo) -> Result<&mut Self> {
+        if tuning_info != TuningInfo::Undefined
+            && self.raw_encoder.api_version() < ApiVersion::V10_0
+        {
+            return Err(NvEncError::UnsupportedByApiVersion);
+        }
         self.tuning_info = tuning_info;
         Ok(self)
     }
 
-    /// Disable writing SPS/PPS (H.264) or VPS/SPS/PPS (HEVC) in the bitstream. Default is enabled.
+    /// Disable writing SPS/PPS (H.264), VPS/SPS/PPS (HEVC) or the sequence header (AV1) in the
+    /// bitstream. Default is enabled.
     pub fn inband_csd(&mut self, enable: bool) -> Result<&mut Self> {
         self.extra_options.inband_csd(enable);
         Ok(self)
     }
 
-    /// Enable writing SPS/PPS (H.264) or VPS/SPS/PPS (HEVC) every IDR frame. Default is disabled.
+    /// Enable writing SPS/PPS (H.264), VPS/SPS/PPS (HEVC) or the sequence header (AV1) every IDR
+    /// or key frame. Default is disabled.
     pub fn repeat_csd(&mut self, enable: bool) -> Result<&mut Self> {
         self.extra_options.repeat_csd(enable);
         Ok(self)
@@ -130,21 +172,362 @@ where
         Ok(self)
     }
 
+    /// Set the strength of spatial adaptive quantization from 1 (least aggressive) to 15 (most
+    /// aggressive). Zero lets the encoder pick. Default is 0.
+    pub fn aq_strength(&mut self, strength: u32) -> Result<&mut Self> {
+        if strength > 15 {
+            return Err(NvEncError::UnsupportedSetting);
+        }
+        self.extra_options.aq_strength(strength);
+        Ok(self)
+    }
+
+    /// Enable temporal adaptive quantization. Default is disabled.
+    pub fn temporal_aq(&mut self, enable: bool) -> Result<&mut Self> {
+        if enable {
+            let caps = self.capabilities(self.codec.ok_or(NvEncError::CodecNotSet)?)?;
+            if !caps.temporal_aq {
+                return Err(NvEncError::UnsupportedSetting);
+            }
+        }
+        self.extra_options.temporal_aq(enable);
+        Ok(self)
+    }
+
+    /// Enable lookahead with the given depth in frames. The depth plus the number of B-frames can
+    /// be at most 31. The ring buffer between the input and the output grows to fit the frames
+    /// held back by the lookahead. Pass `None` to disable this option. Default is disabled.
+    pub fn lookahead(&mut self, depth: Option<u16>) -> Result<&mut Self> {
+        if let Some(depth) = depth {
+            let caps = self.capabilities(self.codec.ok_or(NvEncError::CodecNotSet)?)?;
+            if !caps.lookahead || depth as u32 > MAX_LOOKAHEAD_DEPTH {
+                return Err(NvEncError::UnsupportedSetting);
+            }
+        }
+        self.extra_options.lookahead(depth);
+        Ok(self)
+    }
+
+    /// Allow the lookahead to insert I-frames on scene cuts. Only used with lookahead. Default is
+    /// enabled.
+    pub fn adaptive_i_frames(&mut self, enable: bool) -> Result<&mut Self> {
+        self.extra_options.adaptive_i_frames(enable);
+        Ok(self)
+    }
+
+    /// Allow the lookahead to change the number of consecutive B-frames. Only used with lookahead.
+    /// Default is enabled.
+    pub fn adaptive_b_frames(&mut self, enable: bool) -> Result<&mut Self> {
+        self.extra_options.adaptive_b_frames(enable);
+        Ok(self)
+    }
+
     /// Enable zero-reorder delay. Default is disabled.
     pub fn zero_reorder_delay(&mut self, enable: bool) -> Result<&mut Self> {
         self.extra_options.zero_reorder_delay(enable);
         Ok(self)
     }
 
-    /// Configure with the given `MultiPassSetting`.
+    /// Configure with the given `MultiPassSetting`. Requires NvEnc API 10.0.
     pub fn set_multi_pass(&mut self, multi_pass: MultiPassSetting) -> Result<&mut Self> {
+        if multi_pass != MultiPassSetting::Disabled
+            && self.raw_encoder.api_version() < ApiVersion::V10_0
+        {
+            return Err(NvEncError::UnsupportedByApiVersion);
+        }
         self.extra_options.set_multi_pass(multi_pass);
         Ok(self)
     }
 
+    /// Select the rate control mode, overriding the one from the preset. Returns
+    /// `UnsupportedSetting` if the encoder does not support the mode for the current codec or
+    /// the quality level of `RateControl::TargetQuality` is above 51.
+    pub fn rate_control(&mut self, rate_control: RateControl) -> Result<&mut Self> {
+        if let RateControl::TargetQuality { cq, .. } = rate_control {
+            if cq > 51 {
+                return Err(NvEncError::UnsupportedSetting);
+            }
+        }
+        let caps = self.capabilities(self.codec.ok_or(NvEncError::CodecNotSet)?)?;
+        let mode: crate::sys::NV_ENC_PARAMS_RC_MODE = rate_control.into_nvenc();
+        // Constant QP is zero so it is always supported
+        let mode = mode as u32;
+        if mode != 0 && caps.rate_control_modes & mode == 0 {
+            return Err(NvEncError::UnsupportedSetting);
+        }
+        self.extra_options.set_rate_control(rate_control);
+        Ok(self)
+    }
+
+    /// Set the VBV buffer size and initial delay in bits. Pass `None` to use the defaults of the
+    /// rate control mode.
+    pub fn vbv(&mut self, size_and_initial_delay: Option<(u32, u32)>) -> Result<&mut Self> {
+        if size_and_initial_delay.is_some() {
+            let caps = self.capabilities(self.codec.ok_or(NvEncError::CodecNotSet)?)?;
+            if !caps.custom_vbv_buffer_size {
+                return Err(NvEncError::UnsupportedSetting);
+            }
+        }
+        self.extra_options.vbv(size_and_initial_delay);
+        Ok(self)
+    }
+
+    /// Limit the QP that the rate control can pick. `None` leaves the bound unset. Returns
+    /// `UnsupportedSetting` if a QP of `min_qp` is greater than the same QP of `max_qp`.
+    pub fn qp_range(&mut self, min_qp: Option<Qp>, max_qp: Option<Qp>) -> Result<&mut Self> {
+        if let (Some(min), Some(max)) = (min_qp, max_qp) {
+            if min.i > max.i || min.p > max.p || min.b > max.b {
+                return Err(NvEncError::UnsupportedSetting);
+            }
+        }
+        self.extra_options.qp_range(min_qp, max_qp);
+        Ok(self)
+    }
+
+    /// Set the QP that the rate control starts with. `None` lets the encoder pick one.
+    pub fn initial_qp(&mut self, initial_qp: Option<Qp>) -> Result<&mut Self> {
+        self.extra_options.initial_qp(initial_qp);
+        Ok(self)
+    }
+
+    /// Set the number of frames in a GOP. Pass `None` for an infinite GOP where only the first
+    /// frame is an IDR frame, which cannot be combined with B-frames and disables those of the
+    /// preset. Default is set by the preset.
+    pub fn gop_length(&mut self, gop_length: Option<u32>) -> Result<&mut Self> {
+        self.extra_options
+            .gop_length(gop_length.unwrap_or(crate::sys::NVENC_INFINITE_GOPLENGTH));
+        Ok(self)
+    }
+
+    /// Set the number of frames between IDR frames. Default is the GOP length.
+    pub fn idr_period(&mut self, idr_period: u32) -> Result<&mut Self> {
+        self.extra_options.idr_period(idr_period);
+        Ok(self)
+    }
+
+    /// Set the level of the bitstream, which has to be a level of the codec. Default is
+    /// `Level::Autoselect`, which picks the lowest level that fits the other settings.
+    pub fn level(&mut self, level: Level) -> Result<&mut Self> {
+        let codec = self.codec.ok_or(NvEncError::CodecNotSet)?;
+        // Levels of other codecs do not survive the round trip
+        if Level::from_nvenc(codec, level.into_nvenc(codec)) != level {
+            return Err(NvEncError::UnsupportedSetting);
+        }
+        self.extra_options.level(level);
+        Ok(self)
+    }
+
+    /// Set the number of consecutive B-frames between P-frames. Default is set by the preset.
+    pub fn b_frames(&mut self, b_frames: u32) -> Result<&mut Self> {
+        let caps = self.capabilities(self.codec.ok_or(NvEncError::CodecNotSet)?)?;
+        if b_frames > caps.max_b_frames {
+            return Err(NvEncError::UnsupportedSetting);
+        }
+        self.extra_options.b_frames(b_frames);
+        Ok(self)
+    }
+
+    /// Select which B-frames can be used as references. Default is set by the preset.
+    pub fn b_frame_ref_mode(&mut self, b_frame_ref_mode: BFrameRefMode) -> Result<&mut Self> {
+        let caps = self.capabilities(self.codec.ok_or(NvEncError::CodecNotSet)?)?;
+        let is_supported = match b_frame_ref_mode {
+            BFrameRefMode::Disabled => true,
+            // Same checks as FFmpeg, which treats the capability as a bitmask of the modes
+            BFrameRefMode::Each => caps.b_frame_ref_mode & 1 != 0,
+            BFrameRefMode::Middle => caps.b_frame_ref_mode & 2 != 0,
+        };
+        if !is_supported {
+            return Err(NvEncError::UnsupportedSetting);
+        }
+        self.extra_options.b_frame_ref_mode(b_frame_ref_mode);
+        Ok(self)
+    }
+
+    /// Let the encoder decide the type of each picture. When disabled, the crate picks I- and
+    /// IDR-frames from the GOP length and IDR period and P-frames otherwise, and
+    /// `EncodeFrameOptions::picture_type` can also force P-frames and skipped frames. Cannot be
+    /// disabled together with B-frames or lookahead. Default is enabled.
+    pub fn picture_type_decision(&mut self, enable: bool) -> Result<&mut Self> {
+        self.extra_options.picture_type_decision(enable);
+        Ok(self)
+    }
+
+    /// Encode interlaced content as fields, so that frames can be submitted with a field
+    /// `EncodeFrameOptions::picture_structure`. Only H.264 and only if
+    /// `EncoderCapabilities::field_encoding` is supported, which it is not on recent GPUs.
+    /// Default is disabled.
+    pub fn field_encoding(&mut self, enable: bool) -> Result<&mut Self> {
+        if enable {
+            let codec = self.codec.ok_or(NvEncError::CodecNotSet)?;
+            if codec != Codec::H264 || !self.capabilities(codec)?.field_encoding {
+                return Err(NvEncError::UnsupportedSetting);
+            }
+        }
+        self.extra_options.field_encoding(enable);
+        Ok(self)
+    }
+
+    /// Interpret the `EncodeFrameOptions::qp_map` of each frame as QP offsets or emphasis
+    /// levels. Emphasis levels are only supported by H.264. Pass `None` to ignore QP maps.
+    /// Default is ignored.
+    pub fn qp_map_mode(&mut self, qp_map_mode: Option<QpMapMode>) -> Result<&mut Self> {
+        if let Some(qp_map_mode) = qp_map_mode {
+            let codec = self.codec.ok_or(NvEncError::CodecNotSet)?;
+            let is_supported = match qp_map_mode {
+                QpMapMode::Delta => matches!(codec, Codec::H264 | Codec::Hevc | Codec::Av1),
+                QpMapMode::Emphasis => {
+                    codec == Codec::H264 && self.capabilities(codec)?.emphasis_level_map
+                }
+            };
+            if !is_supported {
+                return Err(NvEncError::UnsupportedSetting);
+            }
+        }
+        self.extra_options.qp_map_mode(qp_map_mode);
+        Ok(self)
+    }
+
+    /// Signal the colour primaries, transfer characteristics, matrix coefficients, range and
+    /// chroma sample location of the video in the VUI. Only H.264 and HEVC. Pass `None` to leave
+    /// them unsignaled. Default is unsignaled.
+    pub fn color_description(
+        &mut self,
+        color_description: Option<ColorDescription>,
+    ) -> Result<&mut Self> {
+        if color_description.is_some() {
+            match self.codec.ok_or(NvEncError::CodecNotSet)? {
+                Codec::H264 | Codec::Hevc => {}
+                _ => return Err(NvEncError::UnsupportedSetting),
+            }
+        }
+        self.extra_options.color_description(color_description);
+        Ok(self)
+    }
+
+    /// Insert a mastering display colour volume SEI message into every frame for HDR10. Only
+    /// HEVC. Pass `None` to disable this option. Default is disabled.
+    pub fn mastering_display(
+        &mut self,
+        mastering_display: Option<MasteringDisplay>,
+    ) -> Result<&mut Self> {
+        if mastering_display.is_some() && self.codec.ok_or(NvEncError::CodecNotSet)? != Codec::Hevc
+        {
+            return Err(NvEncError::UnsupportedSetting);
+        }
+        self.extra_options.mastering_display(mastering_display);
+        Ok(self)
+    }
+
+    /// Insert a content light level information SEI message into every frame for HDR10. Only
+    /// HEVC. Pass `None` to disable this option. Default is disabled.
+    pub fn content_light_level(
+        &mut self,
+        content_light_level: Option<ContentLightLevel>,
+    ) -> Result<&mut Self> {
+        if content_light_level.is_some()
+            && self.codec.ok_or(NvEncError::CodecNotSet)? != Codec::Hevc
+        {
+            return Err(NvEncError::UnsupportedSetting);
+        }
+        self.extra_options.content_light_level(content_light_level);
+        Ok(self)
+    }
+
+    /// Split frames into slices. Only H.264 and HEVC. `SliceMode::MaxBytes` requires support for
+    /// dynamic slice mode. Default is set by the preset.
+    pub fn slice_mode(&mut self, slice_mode: SliceMode) -> Result<&mut Self> {
+        let codec = self.codec.ok_or(NvEncError::CodecNotSet)?;
+        if !matches!(codec, Codec::H264 | Codec::Hevc) {
+            return Err(NvEncError::UnsupportedSetting);
+        }
+        let (_, data) = slice_mode.mode_and_data();
+        if data == 0 {
+            return Err(NvEncError::UnsupportedSetting);
+        }
+        if let SliceMode::MaxBytes(_) = slice_mode {
+            if !self.capabilities(codec)?.dynamic_slice_mode {
+                return Err(NvEncError::UnsupportedSetting);
+            }
+        }
+        self.extra_options.slice_mode(slice_mode);
+        Ok(self)
+    }
+
+    /// Report where each slice starts in the bitstream, see `EncoderOutput::slice_offsets`.
+    /// Requires asynchronous encoding to be disabled. Default is disabled.
+    pub fn report_slice_offsets(&mut self, enable: bool) -> Result<&mut Self> {
+        self.extra_options.report_slice_offsets(enable);
+        Ok(self)
+    }
+
+    /// Refresh frames gradually with a wave of intra-coded blocks that spans `count` frames,
+    /// starting every `period` frames, instead of relying on IDR frames. Only H.264 and HEVC and
+    /// cannot be combined with B-frames. Usually paired with an infinite GOP, see
+    /// `EncoderInput::start_intra_refresh_on_next` to start a wave on demand. Pass `None` to
+    /// disable this option. Default is disabled.
+    pub fn intra_refresh(&mut self, period_and_count: Option<(u32, u32)>) -> Result<&mut Self> {
+        if let Some((period, count)) = period_and_count {
+            let codec = self.codec.ok_or(NvEncError::CodecNotSet)?;
+            if !matches!(codec, Codec::H264 | Codec::Hevc)
+                || !self.capabilities(codec)?.intra_refresh
+                || count == 0
+                || count >= period
+            {
+                return Err(NvEncError::UnsupportedSetting);
+            }
+        }
+        self.extra_options.intra_refresh(period_and_count);
+        Ok(self)
+    }
+
+    /// Set the number of frames kept in the DPB. A large DPB keeps older frames around to predict
+    /// from after newer ones are invalidated with `EncoderInput::invalidate_reference_frames`.
+    /// Only H.264 and HEVC. Default is set by the driver.
+    pub fn dpb_size(&mut self, dpb_size: u32) -> Result<&mut Self> {
+        if !matches!(
+            self.codec.ok_or(NvEncError::CodecNotSet)?,
+            Codec::H264 | Codec::Hevc
+        ) || dpb_size == 0
+        {
+            return Err(NvEncError::UnsupportedSetting);
+        }
+        self.extra_options.dpb_size(dpb_size);
+        Ok(self)
+    }
+
+    /// Keep up to `num_frames` long-term reference frames that can be referenced long after they
+    /// were encoded, e.g. to recover from packet loss without an IDR frame. Frames are marked
+    /// with `EncoderInput::mark_ltr_on_next` unless trust mode is enabled. Only H.264 and HEVC.
+    /// Pass `None` to disable this option. Default is disabled.
+    pub fn long_term_references(&mut self, num_frames: Option<u32>) -> Result<&mut Self> {
+        if let Some(num_frames) = num_frames {
+            let codec = self.codec.ok_or(NvEncError::CodecNotSet)?;
+            if !matches!(codec, Codec::H264 | Codec::Hevc)
+                || num_frames == 0
+                || num_frames > self.capabilities(codec)?.max_ltr_frames
+            {
+                return Err(NvEncError::UnsupportedSetting);
+            }
+        }
+        self.extra_options.long_term_references(num_frames);
+        Ok(self)
+    }
+
+    /// Let the encoder mark the first long-term reference frames after each IDR frame itself
+    /// instead of marking them with `EncoderInput::mark_ltr_on_next`. NVIDIA discourages this
+    /// mode and may remove it. Default is disabled.
+    pub fn ltr_trust_mode(&mut self, enable: bool) -> Result<&mut Self> {
+        self.extra_options.ltr_trust_mode(enable);
+        Ok(self)
+    }
+
     /// Enable filler data insertion. Frame rate needs to be supplied to determine the rate at
     /// which the filler is generated. Pass `None` to disable this option. Default is disabled.
+    /// Requires NvEnc API 9.1.
     pub fn filler_data_insertion(&mut self, frame_rate: Option<(u32, u32)>) -> Result<&mut Self> {
+        if frame_rate.is_some() && self.raw_encoder.api_version() < ApiVersion::V9_1 {
+            return Err(NvEncError::UnsupportedByApiVersion);
+        }
         self.extra_options.filler_data_insertion(frame_rate);
         Ok(self)
     }
@@ -159,6 +542,42 @@ where
         Ok(self)
     }
 
+    /// Split AV1 frames uniformly into the given number of tile columns and rows. Values that are
+    /// not a power of two are rounded down and zero uses the smallest number allowed by the
+    /// standard. Default is `(0, 0)`.
+    #[cfg(feature = "v12_0")]
+    pub fn av1_tiles(&mut self, columns: u32, rows: u32) -> Result<&mut Self> {
+        self.extra_options.av1_tiles(columns, rows);
+        Ok(self)
+    }
+
+    /// Output AV1 in the Annex B length-delimited format instead of the low overhead bitstream
+    /// format. Default is disabled.
+    #[cfg(feature = "v12_0")]
+    pub fn av1_annex_b(&mut self, enable: bool) -> Result<&mut Self> {
+        self.extra_options.annex_b(enable);
+        Ok(self)
+    }
+
+    /// Enable asynchronous encoding where the output is signaled through completion events.
+    /// Only supported on Windows, where it is the default. Disabling it makes
+    /// `EncoderOutput::wait_for_output` block on `NvEncLockBitstream` instead which is mostly
+    /// useful for debugging.
+    pub fn async_encode(&mut self, enable: bool) -> Result<&mut Self> {
+        if enable && !cfg!(windows) {
+            return Err(NvEncError::AsyncEncodeNotSupported);
+        }
+        self.extra_options.async_encode(enable);
+        Ok(self)
+    }
+
+    /// Set how `EncoderInput` waits for a free buffer and `EncoderOutput` waits for the next
+    /// frame. Default is `WaitStrategy::SpinThenBlock`.
+    pub fn wait_strategy(&mut self, wait_strategy: WaitStrategy) -> Result<&mut Self> {
+        self.wait_strategy = wait_strategy;
+        Ok(self)
+    }
+
     /// Build the encoder.
     pub fn build(
         self,
@@ -166,35 +585,127 @@ where
         height: u32,
         texture_format: <D::Buffer as TextureBufferImplTrait>::TextureFormat,
     ) -> Result<(EncoderInput<D>, EncoderOutput)> {
+        let mut encode_params = self.build_encode_params(width, height, &texture_format)?;
+
+        encode_params.initialize_encoder(&self.raw_encoder)?;
+
+        let buffer_size = buffer_size(&encode_params);
+        let texture_buffer =
+            self.device
+                .create_texture_buffer(width, height, texture_format, buffer_size as u32)?;
+
+        let async_encode = self.extra_options.async_encode_enabled();
+        let sei_payloads = self.extra_options.hdr_sei_payloads();
+        let max_slices = if self.extra_options.report_slice_offsets_enabled() {
+            max_slices(width, height)
+        } else {
+            0
+        };
+        let (writer, reader) = encoder_channel(
+            self.raw_encoder,
+            buffer_size,
+            self.wait_strategy,
+            |raw_encoder, i| {
+                EncoderBufferItems::new(
+                    raw_encoder,
+                    texture_buffer.get_texture(i),
+                    texture_buffer.get_pitch_or_subresource_index(i),
+                    async_encode,
+                )
+                .map(|items| items.with_slice_offsets(max_slices))
+            },
+        )?;
+
+        let encoder_input = EncoderInput::new(
+            self.device,
+            writer,
+            InputStaging::Texture(texture_buffer),
+            encode_params,
+            sei_payloads,
+        )?;
+        let encoder_output = EncoderOutput::new(reader);
+        Ok((encoder_input, encoder_output))
+    }
+
+    /// Build an encoder that takes frames from host memory instead of textures. Frames are
+    /// submitted with `EncoderInput::encode_host_frame` and copied into input buffers allocated by
+    /// NvEnc, so the device is only used to open the encode session.
+    pub fn build_with_host_input(
+        self,
+        width: u32,
+        height: u32,
+        buffer_format: BufferFormat,
+    ) -> Result<(EncoderInput<D>, EncoderOutput)> {
+        let mut encode_params = self.build_encode_params(width, height, &buffer_format)?;
+
+        encode_params.initialize_encoder(&self.raw_encoder)?;
+
+        let buffer_size = buffer_size(&encode_params);
+        let async_encode = self.extra_options.async_encode_enabled();
+        let sei_payloads = self.extra_options.hdr_sei_payloads();
+        let max_slices = if self.extra_options.report_slice_offsets_enabled() {
+            max_slices(width, height)
+        } else {
+            0
+        };
+        let (writer, reader) = encoder_channel(
+            self.raw_encoder,
+            buffer_size,
+            self.wait_strategy,
+            |raw_encoder, _| {
+                EncoderBufferItems::with_host_buffer(
+                    raw_encoder,
+                    width,
+                    height,
+                    buffer_format,
+                    async_encode,
+                )
+                .map(|items| items.with_slice_offsets(max_slices))
+            },
+        )?;
+
+        let encoder_input = EncoderInput::new(
+            self.device,
+            writer,
+            InputStaging::Host(buffer_format),
+            encode_params,
+            sei_payloads,
+        )?;
+        let encoder_output = EncoderOutput::new(reader);
+        Ok((encoder_input, encoder_output))
+    }
+
+    fn build_encode_params<T: IntoNvEncBufferFormat>(
+        &self,
+        width: u32,
+        height: u32,
+        buffer_format: &T,
+    ) -> Result<EncodeParams> {
         let codec = self.codec.ok_or(NvEncError::CodecNotSet)?;
         let profile = self.profile;
         let preset = self.preset.ok_or(NvEncError::EncodePresetNotSet)?;
         let tuning_info = self.tuning_info;
 
-        let mut encode_params = EncodeParams::new(
+        if !self.extra_options.is_gop_valid()
+            || !self.extra_options.is_lookahead_valid()
+            || !self.extra_options.is_slice_reporting_valid()
+            || !self.extra_options.is_picture_type_decision_valid()
+        {
+            return Err(NvEncError::UnsupportedSetting);
+        }
+
+        EncodeParams::new(
             &self.raw_encoder,
             width,
             height,
-            &texture_format,
+            buffer_format,
             codec,
             profile,
             preset,
             tuning_info,
             &self.extra_options,
             D::params_require_buffer_format(),
-        )?;
-
-        encode_params.initialize_encoder(&self.raw_encoder)?;
-
-        let texture_buffer =
-            self.device
-                .create_texture_buffer(width, height, texture_format, BUFFER_SIZE as u32)?;
-
-        let (writer, reader) = encoder_channel(self.raw_encoder, &texture_buffer)?;
-
-        let encoder_input = EncoderInput::new(self.device, writer, texture_buffer, encode_params)?;
-        let encoder_output = EncoderOutput::new(reader);
-        Ok((encoder_input, encoder_output))
+        )
     }
 
     /// List all supported codecs (H.264, HEVC, etc.).
@@ -216,13 +727,16 @@ where
             codec_guids.set_len(num_entries.assume_init() as usize);
         }
 
-        let codecs = codec_guids.iter().map(|guid| (*guid).into()).collect();
+        let codecs = codec_guids
+            .iter()
+            .map(|guid| Codec::from_nvenc(*guid))
+            .collect();
         Ok(codecs)
     }
 
     /// Lists the profiles available for a codec.
     pub fn supported_codec_profiles(&self, codec: Codec) -> Result<Vec<CodecProfile>> {
-        let codec = codec.into();
+        let codec = codec.into_nvenc();
         let profile_guid_count = unsafe {
             let mut tmp = MaybeUninit::uninit();
             self.raw_encoder
@@ -242,13 +756,16 @@ where
             profile_guids.set_len(num_entries.assume_init() as usize);
         }
 
-        let codec_profiles = profile_guids.iter().map(|guid| (*guid).into()).collect();
+        let codec_profiles = profile_guids
+            .iter()
+            .map(|guid| CodecProfile::from_nvenc(*guid))
+            .collect();
         Ok(codec_profiles)
     }
 
     /// Lists the encode presets available for a codec.
     pub fn supported_encode_presets(&self, codec: Codec) -> Result<Vec<EncodePreset>> {
-        let codec = codec.into();
+        let codec = codec.into_nvenc();
         let preset_guid_count = unsafe {
             let mut tmp = MaybeUninit::uninit();
             self.raw_encoder
@@ -268,16 +785,22 @@ where
             preset_guids.set_len(num_entries.assume_init() as usize);
         }
 
-        let presets = preset_guids.iter().map(|guid| (*guid).into()).collect();
+        let presets = preset_guids
+            .iter()
+            .map(|guid| EncodePreset::from_nvenc(*guid))
+            .collect();
         Ok(presets)
     }
 
-    /// Lists the supported input formats for a given codec.
-    pub fn supported_input_formats(
-        &self,
-        codec: Codec,
-    ) -> Result<Vec<crate::sys::NV_ENC_BUFFER_FORMAT>> {
-        let codec = codec.into();
+    /// Queries what the encoder supports for a given codec.
+    pub fn capabilities(&self, codec: Codec) -> Result<EncoderCapabilities> {
+        EncoderCapabilities::query(&self.raw_encoder, codec)
+    }
+
+    /// Lists the supported input formats for a given codec. Formats that this library does not
+    /// know about are left out.
+    pub fn supported_input_formats(&self, codec: Codec) -> Result<Vec<BufferFormat>> {
+        let codec = codec.into_nvenc();
         let mut tmp = MaybeUninit::uninit();
         let input_format_count = unsafe {
             self.raw_encoder
@@ -296,6 +819,9 @@ where
             )?;
             input_formats.set_len(num_entries.assume_init() as usize);
         }
-        Ok(input_formats)
+        Ok(input_formats
+            .iter()
+            .filter_map(|format| BufferFormat::try_from_nvenc(format).ok())
+            .collect())
     }
 }
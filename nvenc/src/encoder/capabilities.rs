@@ -0,0 +1,160 @@
+use super::{api_version::ApiVersion, raw_encoder::RawEncoder};
+use crate::{Codec, Level, Result};
+use std::mem::MaybeUninit;
+
+/// What the encoder of the current device supports for a codec, as reported by
+/// `NvEncGetEncodeCaps`.
+///
+/// Capabilities that were added after the negotiated API version are reported as zero/`false`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub struct EncoderCapabilities {
+    pub min_width: u32,
+    pub min_height: u32,
+    pub max_width: u32,
+    pub max_height: u32,
+    /// Maximum number of macroblocks (H.264) or CTBs (HEVC, AV1) in a frame.
+    pub max_macroblocks: u32,
+    /// Maximum number of macroblocks per second, i.e. the throughput of a single session.
+    pub max_macroblocks_per_second: u32,
+    /// Maximum number of consecutive B-frames. B-frames are not supported if zero.
+    pub max_b_frames: u32,
+    /// Bitmask of the supported `BFrameRefMode`s: 1 if every B-frame can be used as a reference
+    /// and 2 if the middle B-frame can be. 0 if B-frames cannot be used as references.
+    pub b_frame_ref_mode: u32,
+    /// Whether more than one reference frame can be used for P-frames.
+    pub multiple_ref_frames: bool,
+    /// Maximum number of long-term reference frames.
+    pub max_ltr_frames: u32,
+    /// Bitmask of the supported `NV_ENC_PARAMS_RC_MODE` values. Constant QP is always supported.
+    pub rate_control_modes: u32,
+    pub custom_vbv_buffer_size: bool,
+    pub lookahead: bool,
+    pub temporal_aq: bool,
+    pub emphasis_level_map: bool,
+    pub weighted_prediction: bool,
+    pub max_temporal_layers: u32,
+    pub hierarchical_p_frames: bool,
+    pub hierarchical_b_frames: bool,
+    pub temporal_svc: bool,
+    pub min_level: Level,
+    pub max_level: Level,
+    /// Whether interlaced content can be encoded as fields, see `EncoderBuilder::field_encoding`.
+    pub field_encoding: bool,
+    pub monochrome: bool,
+    pub yuv444: bool,
+    pub ten_bit: bool,
+    pub lossless: bool,
+    pub alpha_layer: bool,
+    pub sample_adaptive_offset: bool,
+    pub intra_refresh: bool,
+    pub single_slice_intra_refresh: bool,
+    pub dynamic_slice_mode: bool,
+    pub ref_pic_invalidation: bool,
+    pub dynamic_resolution_change: bool,
+    pub dynamic_bitrate_change: bool,
+    pub dynamic_force_const_qp: bool,
+    pub dynamic_rate_control_mode_change: bool,
+    pub async_encode: bool,
+    pub encoder_engines: u32,
+}
+
+impl EncoderCapabilities {
+    pub(crate) fn query(raw_encoder: &RawEncoder, codec: Codec) -> Result<Self> {
+        use crate::sys::NV_ENC_CAPS;
+
+        let query = |caps: NV_ENC_CAPS| -> Result<u32> {
+            let mut caps_param: crate::sys::NV_ENC_CAPS_PARAM =
+                unsafe { MaybeUninit::zeroed().assume_init() };
+            caps_param.version = raw_encoder.struct_versions().caps_param;
+            caps_param.capsToQuery = caps;
+
+            let mut caps_val = 0;
+            unsafe {
+                raw_encoder.get_encode_caps(codec.into_nvenc(), &mut caps_param, &mut caps_val)?;
+            }
+            Ok(caps_val as u32)
+        };
+        // Older drivers reject the capabilities they do not know about
+        let query_since = |api_version: ApiVersion, caps: NV_ENC_CAPS| -> Result<u32> {
+            if raw_encoder.api_version() >= api_version {
+                query(caps)
+            } else {
+                Ok(0)
+            }
+        };
+        let is_supported = |caps: NV_ENC_CAPS| query(caps).map(|val| val != 0);
+
+        Ok(EncoderCapabilities {
+            min_width: query_since(ApiVersion::V9_1, NV_ENC_CAPS::NV_ENC_CAPS_WIDTH_MIN)?,
+            min_height: query_since(ApiVersion::V9_1, NV_ENC_CAPS::NV_ENC_CAPS_HEIGHT_MIN)?,
+            max_width: query(NV_ENC_CAPS::NV_ENC_CAPS_WIDTH_MAX)?,
+            max_height: query(NV_ENC_CAPS::NV_ENC_CAPS_HEIGHT_MAX)?,
+            max_macroblocks: query(NV_ENC_CAPS::NV_ENC_CAPS_MB_NUM_MAX)?,
+            max_macroblocks_per_second: query(NV_ENC_CAPS::NV_ENC_CAPS_MB_PER_SEC_MAX)?,
+            max_b_frames: query(NV_ENC_CAPS::NV_ENC_CAPS_NUM_MAX_BFRAMES)?,
+            b_frame_ref_mode: query(NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_BFRAME_REF_MODE)?,
+            multiple_ref_frames: query_since(
+                ApiVersion::V9_1,
+                NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_MULTIPLE_REF_FRAMES,
+            )? != 0,
+            max_ltr_frames: query(NV_ENC_CAPS::NV_ENC_CAPS_NUM_MAX_LTR_FRAMES)?,
+            rate_control_modes: query(NV_ENC_CAPS::NV_ENC_CAPS_SUPPORTED_RATECONTROL_MODES)?,
+            custom_vbv_buffer_size: is_supported(
+                NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_CUSTOM_VBV_BUF_SIZE,
+            )?,
+            lookahead: is_supported(NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_LOOKAHEAD)?,
+            temporal_aq: is_supported(NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_TEMPORAL_AQ)?,
+            emphasis_level_map: is_supported(NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_EMPHASIS_LEVEL_MAP)?,
+            weighted_prediction: is_supported(
+                NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_WEIGHTED_PREDICTION,
+            )?,
+            max_temporal_layers: query(NV_ENC_CAPS::NV_ENC_CAPS_NUM_MAX_TEMPORAL_LAYERS)?,
+            hierarchical_p_frames: is_supported(
+                NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_HIERARCHICAL_PFRAMES,
+            )?,
+            hierarchical_b_frames: is_supported(
+                NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_HIERARCHICAL_BFRAMES,
+            )?,
+            temporal_svc: is_supported(NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_TEMPORAL_SVC)?,
+            min_level: Level::from_nvenc(codec, query(NV_ENC_CAPS::NV_ENC_CAPS_LEVEL_MIN)?),
+            max_level: Level::from_nvenc(codec, query(NV_ENC_CAPS::NV_ENC_CAPS_LEVEL_MAX)?),
+            field_encoding: is_supported(NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_FIELD_ENCODING)?,
+            monochrome: is_supported(NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_MONOCHROME)?,
+            yuv444: is_supported(NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_YUV444_ENCODE)?,
+            ten_bit: is_supported(NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_10BIT_ENCODE)?,
+            lossless: is_supported(NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_LOSSLESS_ENCODE)?,
+            alpha_layer: query_since(
+                ApiVersion::V11_0,
+                NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_ALPHA_LAYER_ENCODING,
+            )? != 0,
+            sample_adaptive_offset: is_supported(NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_SAO)?,
+            intra_refresh: is_supported(NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_INTRA_REFRESH)?,
+            single_slice_intra_refresh: query_since(
+                ApiVersion::V11_1,
+                NV_ENC_CAPS::NV_ENC_CAPS_SINGLE_SLICE_INTRA_REFRESH,
+            )? != 0,
+            dynamic_slice_mode: is_supported(NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_DYNAMIC_SLICE_MODE)?,
+            ref_pic_invalidation: is_supported(
+                NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_REF_PIC_INVALIDATION,
+            )?,
+            dynamic_resolution_change: is_supported(
+                NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_DYN_RES_CHANGE,
+            )?,
+            dynamic_bitrate_change: is_supported(
+                NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_DYN_BITRATE_CHANGE,
+            )?,
+            dynamic_force_const_qp: is_supported(
+                NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_DYN_FORCE_CONSTQP,
+            )?,
+            dynamic_rate_control_mode_change: is_supported(
+                NV_ENC_CAPS::NV_ENC_CAPS_SUPPORT_DYN_RCMODE_CHANGE,
+            )?,
+            async_encode: is_supported(NV_ENC_CAPS::NV_ENC_CAPS_ASYNC_ENCODE_SUPPORT)?,
+            encoder_engines: query_since(
+                ApiVersion::V11_0,
+                NV_ENC_CAPS::NV_ENC_CAPS_NUM_ENCODER_ENGINES,
+            )?,
+        })
+    }
+}
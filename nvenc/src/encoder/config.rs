@@ -1,7 +1,14 @@
-use super::{raw_encoder::RawEncoder, texture::IntoNvEncBufferFormat};
-use crate::{Codec, CodecProfile, EncodePreset, MultiPassSetting, Result, TuningInfo};
+use super::{api_version::ApiVersion, raw_encoder::RawEncoder, texture::IntoNvEncBufferFormat};
+use crate::{
+    BFrameRefMode, Codec, CodecProfile, ColorDescription, ContentLightLevel, EncodePreset, Level,
+    MasteringDisplay, MultiPassSetting, Qp, QpMapMode, RateControl, Result, SeiPayload, SliceMode,
+    TuningInfo,
+};
 use std::{mem::MaybeUninit, ptr::addr_of_mut};
 
+/// Upper bound of the lookahead depth plus the number of B-frames.
+pub const MAX_LOOKAHEAD_DEPTH: u32 = 31;
+
 #[repr(transparent)]
 pub struct EncodeParams(crate::sys::NV_ENC_RECONFIGURE_PARAMS);
 
@@ -34,27 +41,22 @@ impl EncodeParams {
     ) -> Result<Self> {
         let mut reconfig_params: crate::sys::NV_ENC_RECONFIGURE_PARAMS =
             unsafe { MaybeUninit::zeroed().assume_init() };
-        reconfig_params.version = crate::sys::NV_ENC_RECONFIGURE_PARAMS_VER;
+        reconfig_params.version = raw_encoder.struct_versions().reconfigure_params;
 
         let init_params = &mut reconfig_params.reInitEncodeParams;
-        init_params.version = crate::sys::NV_ENC_INITIALIZE_PARAMS_VER;
-        init_params.encodeGUID = codec.into();
-        init_params.presetGUID = preset.into();
+        init_params.version = raw_encoder.struct_versions().initialize_params;
+        init_params.encodeGUID = codec.into_nvenc();
+        init_params.presetGUID = preset.into_nvenc();
         init_params.encodeWidth = width;
         init_params.encodeHeight = height;
-        init_params.enablePTD = 1;
-        init_params.tuningInfo = tuning_info.into();
+        init_params.tuningInfo = tuning_info.into_nvenc();
 
         #[cfg(windows)]
         {
-            // The latency is orders of magnitude higher if synchronous encoding mode is used on
-            // Windows based both on testing and according to the docs:
-            // https://docs.nvidia.com/video-technologies/video-codec-sdk/nvenc-video-encoder-api-prog-guide/
-            init_params.enableEncodeAsync = 1;
             init_params.set_enableOutputInVidmem(0);
 
-            // If DirectX12, bufferFormat must be set
-            if params_require_buffer_format {
+            // If DirectX12, bufferFormat must be set. The field was added in 11.1.
+            if params_require_buffer_format && raw_encoder.api_version() >= ApiVersion::V11_1 {
                 init_params.bufferFormat = texture_format.into_nvenc_buffer_format();
             }
         }
@@ -81,6 +83,9 @@ impl EncodeParams {
         unsafe { raw_encoder.initialize_encoder(&mut self.0.reInitEncodeParams) }
     }
 
+    /// Change the bitrate of CBR or VBR rate control. Returns `UnsupportedSetting` for constant QP
+    /// and for VBR without an average, i.e. `RateControl::TargetQuality`, which would turn into
+    /// plain VBR. The previous rate control is kept if the encoder cannot be reconfigured.
     pub fn set_average_bitrate(
         &mut self,
         raw_encoder: &RawEncoder,
@@ -94,15 +99,50 @@ impl EncodeParams {
         );
 
         let encoder_config = unsafe { &mut *ptr };
+        match encoder_config.rcParams.rateControlMode {
+            crate::sys::NV_ENC_PARAMS_RC_MODE::NV_ENC_PARAMS_RC_CONSTQP => {
+                return Err(crate::NvEncError::UnsupportedSetting)
+            }
+            crate::sys::NV_ENC_PARAMS_RC_MODE::NV_ENC_PARAMS_RC_VBR
+                if encoder_config.rcParams.averageBitRate == 0 =>
+            {
+                return Err(crate::NvEncError::UnsupportedSetting)
+            }
+            _ => {}
+        }
+
+        let rc_params = &encoder_config.rcParams;
+        let previous_rc_params = (
+            rc_params.averageBitRate,
+            rc_params.maxBitRate,
+            rc_params.vbvBufferSize,
+            rc_params.vbvInitialDelay,
+        );
         encoder_config.rcParams.averageBitRate = bitrate;
-        encoder_config.rcParams.maxBitRate = bitrate;
+        // VBR keeps its peak unless the new average is above it
+        if encoder_config.rcParams.rateControlMode
+            != crate::sys::NV_ENC_PARAMS_RC_MODE::NV_ENC_PARAMS_RC_VBR
+            || encoder_config.rcParams.maxBitRate < bitrate
+        {
+            encoder_config.rcParams.maxBitRate = bitrate;
+        }
 
         if let Some(vbv_buffer_size) = vbv_buffer_size {
             encoder_config.rcParams.vbvBufferSize = vbv_buffer_size;
             encoder_config.rcParams.vbvInitialDelay = vbv_buffer_size;
         }
 
-        unsafe { raw_encoder.reconfigure_encoder(&mut self.0) }
+        let result = unsafe { raw_encoder.reconfigure_encoder(&mut self.0) };
+        if result.is_err() {
+            let rc_params = &mut encoder_config.rcParams;
+            (
+                rc_params.averageBitRate,
+                rc_params.maxBitRate,
+                rc_params.vbvBufferSize,
+                rc_params.vbvInitialDelay,
+            ) = previous_rc_params;
+        }
+        result
     }
 
     pub fn encode_width(&self) -> u32 {
@@ -112,6 +152,156 @@ impl EncodeParams {
     pub fn encode_height(&self) -> u32 {
         self.0.reInitEncodeParams.encodeHeight
     }
+
+    pub fn codec(&self) -> Codec {
+        Codec::from_nvenc(self.0.reInitEncodeParams.encodeGUID)
+    }
+
+    /// Number of frames in the DPB, or `None` if it was left to the driver or the codec is
+    /// not H.264 or HEVC.
+    pub fn dpb_size(&self) -> Option<u32> {
+        let encoder_config = unsafe { &*self.0.reInitEncodeParams.encodeConfig };
+        let codec_config = &encoder_config.encodeCodecConfig;
+        let dpb_size = match self.codec() {
+            Codec::H264 => unsafe { codec_config.h264Config.as_ref() }.maxNumRefFrames,
+            Codec::Hevc => unsafe { codec_config.hevcConfig.as_ref() }.maxNumRefFramesInDPB,
+            _ => 0,
+        };
+        (dpb_size != 0).then_some(dpb_size)
+    }
+
+    pub fn idr_period(&self) -> u32 {
+        let encoder_config = unsafe { &*self.0.reInitEncodeParams.encodeConfig };
+        let codec_config = &encoder_config.encodeCodecConfig;
+        match self.codec() {
+            Codec::H264 => unsafe { codec_config.h264Config.as_ref() }.idrPeriod,
+            Codec::Hevc => unsafe { codec_config.hevcConfig.as_ref() }.idrPeriod,
+            _ => encoder_config.gopLength,
+        }
+    }
+
+    pub fn has_b_frames(&self) -> bool {
+        let encoder_config = unsafe { &*self.0.reInitEncodeParams.encodeConfig };
+        encoder_config.frameIntervalP > 1
+    }
+
+    /// Number of long-term reference frames and whether they are marked in trust mode, or `None`
+    /// if LTR is disabled.
+    pub fn ltr_config(&self) -> Option<(u32, bool)> {
+        let encoder_config = unsafe { &*self.0.reInitEncodeParams.encodeConfig };
+        let codec_config = &encoder_config.encodeCodecConfig;
+        let (enabled, num_frames, trust_mode) = match self.codec() {
+            Codec::H264 => {
+                let h264_config = unsafe { codec_config.h264Config.as_ref() };
+                (
+                    h264_config.enableLTR(),
+                    h264_config.ltrNumFrames,
+                    h264_config.ltrTrustMode,
+                )
+            }
+            Codec::Hevc => {
+                let hevc_config = unsafe { codec_config.hevcConfig.as_ref() };
+                (
+                    hevc_config.enableLTR(),
+                    hevc_config.ltrNumFrames,
+                    hevc_config.ltrTrustMode,
+                )
+            }
+            _ => (0, 0, 0),
+        };
+        (enabled != 0).then_some((num_frames, trust_mode != 0))
+    }
+
+    /// Number of frames an intra refresh wave spans, or `None` if intra refresh is disabled.
+    pub fn intra_refresh_count(&self) -> Option<u32> {
+        let encoder_config = unsafe { &*self.0.reInitEncodeParams.encodeConfig };
+        let codec_config = &encoder_config.encodeCodecConfig;
+        let (enabled, count) = match self.codec() {
+            Codec::H264 => {
+                let h264_config = unsafe { codec_config.h264Config.as_ref() };
+                (
+                    h264_config.enableIntraRefresh(),
+                    h264_config.intraRefreshCnt,
+                )
+            }
+            Codec::Hevc => {
+                let hevc_config = unsafe { codec_config.hevcConfig.as_ref() };
+                (
+                    hevc_config.enableIntraRefresh(),
+                    hevc_config.intraRefreshCnt,
+                )
+            }
+            _ => (0, 0),
+        };
+        (enabled != 0).then_some(count)
+    }
+
+    /// Number of frames between I-frames.
+    pub fn gop_length(&self) -> u32 {
+        let encoder_config = unsafe { &*self.0.reInitEncodeParams.encodeConfig };
+        encoder_config.gopLength
+    }
+
+    /// Whether the encoder decides the picture types instead of the client.
+    pub fn picture_type_decision_enabled(&self) -> bool {
+        self.0.reInitEncodeParams.enablePTD != 0
+    }
+
+    /// The QP of the rate control, or `None` if it is not constant QP.
+    pub fn const_qp(&self) -> Option<Qp> {
+        let encoder_config = unsafe { &*self.0.reInitEncodeParams.encodeConfig };
+        let rc_params = &encoder_config.rcParams;
+        (rc_params.rateControlMode == crate::sys::NV_ENC_PARAMS_RC_MODE::NV_ENC_PARAMS_RC_CONSTQP)
+            .then_some(Qp {
+                i: rc_params.constQP.qpIntra,
+                p: rc_params.constQP.qpInterP,
+                b: rc_params.constQP.qpInterB,
+            })
+    }
+
+    /// Whether frames can be submitted as two fields.
+    pub fn field_encoding_enabled(&self) -> bool {
+        let encoder_config = unsafe { &*self.0.reInitEncodeParams.encodeConfig };
+        encoder_config.frameFieldMode
+            == crate::sys::NV_ENC_PARAMS_FRAME_FIELD_MODE::NV_ENC_PARAMS_FRAME_FIELD_MODE_FIELD
+    }
+
+    /// How QP maps are interpreted, or `None` if they are ignored.
+    pub fn qp_map_mode(&self) -> Option<QpMapMode> {
+        use crate::sys::NV_ENC_QP_MAP_MODE;
+
+        let encoder_config = unsafe { &*self.0.reInitEncodeParams.encodeConfig };
+        match encoder_config.rcParams.qpMapMode {
+            NV_ENC_QP_MAP_MODE::NV_ENC_QP_MAP_DELTA => Some(QpMapMode::Delta),
+            NV_ENC_QP_MAP_MODE::NV_ENC_QP_MAP_EMPHASIS => Some(QpMapMode::Emphasis),
+            _ => None,
+        }
+    }
+
+    /// Change the QP of constant QP rate control. The previous QP is kept if the encoder cannot
+    /// be reconfigured.
+    pub fn set_const_qp(&mut self, raw_encoder: &RawEncoder, qp: Qp) -> Result<()> {
+        let encoder_config = unsafe { &mut *self.0.reInitEncodeParams.encodeConfig };
+        let previous_qp = std::mem::replace(&mut encoder_config.rcParams.constQP, qp.into_nvenc());
+        let result = unsafe { raw_encoder.reconfigure_encoder(&mut self.0) };
+        if result.is_err() {
+            encoder_config.rcParams.constQP = previous_qp;
+        }
+        result
+    }
+
+    /// Number of frames the encoder can hold on to before the first output is ready: the
+    /// B-frames that are reordered and the frames in the lookahead.
+    pub fn frames_held_by_encoder(&self) -> usize {
+        let encoder_config = unsafe { &*self.0.reInitEncodeParams.encodeConfig };
+        let b_frames = (encoder_config.frameIntervalP - 1).max(0) as usize;
+        let lookahead_depth = if encoder_config.rcParams.enableLookahead() != 0 {
+            encoder_config.rcParams.lookaheadDepth as usize
+        } else {
+            0
+        };
+        b_frames + lookahead_depth
+    }
 }
 
 fn build_encode_config<T: IntoNvEncBufferFormat>(
@@ -128,21 +318,27 @@ fn build_encode_config<T: IntoNvEncBufferFormat>(
 
         let ptr = tmp.as_mut_ptr();
 
-        addr_of_mut!((*ptr).version).write(crate::sys::NV_ENC_PRESET_CONFIG_VER);
-        addr_of_mut!((*ptr).presetCfg.version).write(crate::sys::NV_ENC_CONFIG_VER);
-        raw_encoder.get_encode_preset_config_ex(
-            codec.into(),
-            preset.into(),
-            tuning_info.into(),
-            ptr,
-        )?;
+        let struct_versions = raw_encoder.struct_versions();
+        addr_of_mut!((*ptr).version).write(struct_versions.preset_config);
+        addr_of_mut!((*ptr).presetCfg.version).write(struct_versions.config);
+        // Tuning info was introduced together with `NvEncGetEncodePresetConfigEx` in 10.0
+        if raw_encoder.api_version() >= ApiVersion::V10_0 {
+            raw_encoder.get_encode_preset_config_ex(
+                codec.into_nvenc(),
+                preset.into_nvenc(),
+                tuning_info.into_nvenc(),
+                ptr,
+            )?;
+        } else {
+            raw_encoder.get_encode_preset_config(codec.into_nvenc(), preset.into_nvenc(), ptr)?;
+        }
         tmp.assume_init().presetCfg
     };
 
-    // Need to set the profile after `NvEncGetEncodePresetConfigEx` because it will get wiped
+    // Need to set the profile after `NvEncGetEncodePresetConfig(Ex)` because it will get wiped
     // otherwise. A zeroed GUID is a valid value for the profileGUID in which case the encoder
     // autoselects a profile.
-    encode_config.profileGUID = profile.into();
+    encode_config.profileGUID = profile.into_nvenc();
 
     extra_options.modify_encode_config(&mut encode_config);
 
@@ -169,7 +365,10 @@ fn build_encode_config<T: IntoNvEncBufferFormat>(
                 h264_config.set_outputFramePackingSEI(0);
                 h264_config.set_outputRecoveryPointSEI(0);
                 h264_config.set_enableScalabilityInfoSEI(0);
-                h264_config.set_disableSVCPrefixNalu(1);
+                // Reserved before 11.0
+                if raw_encoder.api_version() >= ApiVersion::V11_0 {
+                    h264_config.set_disableSVCPrefixNalu(1);
+                }
             }
         }
         Codec::Hevc => {
@@ -191,6 +390,28 @@ fn build_encode_config<T: IntoNvEncBufferFormat>(
                 hevc_config.set_enableAlphaLayerEncoding(0);
             }
         }
+        #[cfg(feature = "v12_0")]
+        Codec::Av1 => {
+            let av1_config = unsafe { &mut codec_config.av1Config.as_mut() };
+
+            extra_options.modify_av1_encode_config(av1_config);
+
+            // Only 4:2:0 is supported. Encode with the bit depth of the input.
+            let nvenc_format = texture_format.into_nvenc_buffer_format();
+            let bit_depth_minus_8 = pixel_bit_depth_minus_8(&nvenc_format);
+            av1_config.set_chromaFormatIDC(1);
+            av1_config.set_inputPixelBitDepthMinus8(bit_depth_minus_8);
+            av1_config.set_pixelBitDepthMinus8(bit_depth_minus_8);
+        }
+        #[cfg(not(feature = "v12_0"))]
+        Codec::Av1 => return Err(crate::NvEncError::UnsupportedByApiVersion),
+        // There is no way to fill in the codec-specific config of a codec that is not known
+        Codec::Unknown(_) => return Err(crate::NvEncError::UnsupportedCodec),
+    }
+
+    // The encoder silently ignores intra refresh if the GOP has B-frames
+    if extra_options.intra_refresh_enabled() && encode_config.frameIntervalP > 1 {
+        return Err(crate::NvEncError::UnsupportedSetting);
     }
 
     Ok(Box::new(encode_config))
@@ -200,11 +421,43 @@ pub struct ExtraOptions {
     inband_csd_disabled: u32,
     csd_should_repeat: u32,
     spatial_aq_enabled: u32,
+    aq_strength: u32,
+    temporal_aq_enabled: u32,
+    lookahead_depth: Option<u16>,
+    i_adapt_disabled: u32,
+    b_adapt_disabled: u32,
     zero_reorder_delay_enabled: u32,
     multi_pass: MultiPassSetting,
     filler_data_frame_rate: Option<(u32, u32)>,
     filler_data_enabled: u32,
     display_aspect_ratio: Option<(u32, u32)>,
+    async_encode_enabled: u32,
+    rate_control: Option<RateControl>,
+    vbv: Option<(u32, u32)>,
+    min_qp: Option<Qp>,
+    max_qp: Option<Qp>,
+    initial_qp: Option<Qp>,
+    gop_length: Option<u32>,
+    idr_period: Option<u32>,
+    level: Option<Level>,
+    b_frames: Option<u32>,
+    b_frame_ref_mode: Option<BFrameRefMode>,
+    color_description: Option<ColorDescription>,
+    mastering_display: Option<MasteringDisplay>,
+    content_light_level: Option<ContentLightLevel>,
+    slice_mode: Option<SliceMode>,
+    report_slice_offsets: u32,
+    intra_refresh: Option<(u32, u32)>,
+    ltr_num_frames: Option<u32>,
+    ltr_trust_mode: u32,
+    dpb_size: Option<u32>,
+    picture_type_decision_enabled: u32,
+    field_encoding_enabled: bool,
+    qp_map_mode: Option<QpMapMode>,
+    #[cfg(feature = "v12_0")]
+    av1_tiles: (u32, u32),
+    #[cfg(feature = "v12_0")]
+    annex_b_enabled: u32,
 }
 
 impl Default for ExtraOptions {
@@ -213,11 +466,46 @@ impl Default for ExtraOptions {
             inband_csd_disabled: 0,
             csd_should_repeat: 0,
             spatial_aq_enabled: 0,
+            aq_strength: 0,
+            temporal_aq_enabled: 0,
+            lookahead_depth: None,
+            i_adapt_disabled: 0,
+            b_adapt_disabled: 0,
             zero_reorder_delay_enabled: 0,
             multi_pass: MultiPassSetting::Disabled,
             filler_data_frame_rate: None,
             filler_data_enabled: 0,
             display_aspect_ratio: None,
+            // The latency is orders of magnitude higher if synchronous encoding mode is used on
+            // Windows based both on testing and according to the docs:
+            // https://docs.nvidia.com/video-technologies/video-codec-sdk/nvenc-video-encoder-api-prog-guide/
+            async_encode_enabled: if cfg!(windows) { 1 } else { 0 },
+            rate_control: None,
+            vbv: None,
+            min_qp: None,
+            max_qp: None,
+            initial_qp: None,
+            gop_length: None,
+            idr_period: None,
+            level: None,
+            b_frames: None,
+            b_frame_ref_mode: None,
+            color_description: None,
+            mastering_display: None,
+            content_light_level: None,
+            slice_mode: None,
+            report_slice_offsets: 0,
+            intra_refresh: None,
+            ltr_num_frames: None,
+            ltr_trust_mode: 0,
+            dpb_size: None,
+            picture_type_decision_enabled: 1,
+            field_encoding_enabled: false,
+            qp_map_mode: None,
+            #[cfg(feature = "v12_0")]
+            av1_tiles: (0, 0),
+            #[cfg(feature = "v12_0")]
+            annex_b_enabled: 0,
         }
     }
 }
@@ -236,6 +524,38 @@ impl ExtraOptions {
         self.spatial_aq_enabled = if enable { 1 } else { 0 };
     }
 
+    /// Zero lets the encoder pick the strength.
+    pub(crate) fn aq_strength(&mut self, strength: u32) {
+        self.aq_strength = strength;
+    }
+
+    pub(crate) fn temporal_aq(&mut self, enable: bool) {
+        self.temporal_aq_enabled = if enable { 1 } else { 0 };
+    }
+
+    pub(crate) fn lookahead(&mut self, depth: Option<u16>) {
+        self.lookahead_depth = depth;
+    }
+
+    pub(crate) fn adaptive_i_frames(&mut self, enable: bool) {
+        // Reverse 0/1 here
+        self.i_adapt_disabled = if enable { 0 } else { 1 };
+    }
+
+    pub(crate) fn adaptive_b_frames(&mut self, enable: bool) {
+        // Reverse 0/1 here
+        self.b_adapt_disabled = if enable { 0 } else { 1 };
+    }
+
+    /// The lookahead and the B-frames share a window of `MAX_LOOKAHEAD_DEPTH` frames. B-frames
+    /// left to the preset are checked by the encoder instead.
+    pub(crate) fn is_lookahead_valid(&self) -> bool {
+        match self.lookahead_depth {
+            Some(depth) => depth as u32 + self.b_frames.unwrap_or(0) <= MAX_LOOKAHEAD_DEPTH,
+            None => true,
+        }
+    }
+
     pub(crate) fn zero_reorder_delay(&mut self, enable: bool) {
         self.zero_reorder_delay_enabled = if enable { 1 } else { 0 };
     }
@@ -253,7 +573,166 @@ impl ExtraOptions {
         self.display_aspect_ratio = display_aspect_ratio;
     }
 
+    pub(crate) fn async_encode(&mut self, enable: bool) {
+        self.async_encode_enabled = if enable { 1 } else { 0 };
+    }
+
+    pub(crate) fn async_encode_enabled(&self) -> bool {
+        self.async_encode_enabled != 0
+    }
+
+    pub(crate) fn set_rate_control(&mut self, rate_control: RateControl) {
+        self.rate_control = Some(rate_control);
+    }
+
+    pub(crate) fn vbv(&mut self, vbv: Option<(u32, u32)>) {
+        self.vbv = vbv;
+    }
+
+    pub(crate) fn qp_range(&mut self, min_qp: Option<Qp>, max_qp: Option<Qp>) {
+        self.min_qp = min_qp;
+        self.max_qp = max_qp;
+    }
+
+    pub(crate) fn initial_qp(&mut self, initial_qp: Option<Qp>) {
+        self.initial_qp = initial_qp;
+    }
+
+    /// `NVENC_INFINITE_GOPLENGTH` for an infinite GOP.
+    pub(crate) fn gop_length(&mut self, gop_length: u32) {
+        self.gop_length = Some(gop_length);
+    }
+
+    pub(crate) fn idr_period(&mut self, idr_period: u32) {
+        self.idr_period = Some(idr_period);
+    }
+
+    pub(crate) fn level(&mut self, level: Level) {
+        self.level = Some(level);
+    }
+
+    pub(crate) fn b_frames(&mut self, b_frames: u32) {
+        self.b_frames = Some(b_frames);
+    }
+
+    pub(crate) fn b_frame_ref_mode(&mut self, b_frame_ref_mode: BFrameRefMode) {
+        self.b_frame_ref_mode = Some(b_frame_ref_mode);
+    }
+
+    /// P-frames are the only option in an infinite GOP.
+    pub(crate) fn is_gop_valid(&self) -> bool {
+        self.gop_length != Some(crate::sys::NVENC_INFINITE_GOPLENGTH)
+            || self.b_frames.unwrap_or(0) == 0
+    }
+
+    /// The IDR period follows the GOP length unless it was set explicitly. Leaving it to the
+    /// encoder does not work since the presets set it to their own GOP length.
+    fn idr_period_or_gop_length(&self) -> Option<u32> {
+        self.idr_period.or(self.gop_length)
+    }
+
+    pub(crate) fn color_description(&mut self, color_description: Option<ColorDescription>) {
+        self.color_description = color_description;
+    }
+
+    pub(crate) fn mastering_display(&mut self, mastering_display: Option<MasteringDisplay>) {
+        self.mastering_display = mastering_display;
+    }
+
+    pub(crate) fn content_light_level(&mut self, content_light_level: Option<ContentLightLevel>) {
+        self.content_light_level = content_light_level;
+    }
+
+    pub(crate) fn slice_mode(&mut self, slice_mode: SliceMode) {
+        self.slice_mode = Some(slice_mode);
+    }
+
+    pub(crate) fn report_slice_offsets(&mut self, enable: bool) {
+        self.report_slice_offsets = if enable { 1 } else { 0 };
+    }
+
+    pub(crate) fn report_slice_offsets_enabled(&self) -> bool {
+        self.report_slice_offsets != 0
+    }
+
+    /// `(period, count)`
+    pub(crate) fn intra_refresh(&mut self, intra_refresh: Option<(u32, u32)>) {
+        self.intra_refresh = intra_refresh;
+    }
+
+    fn intra_refresh_enabled(&self) -> bool {
+        self.intra_refresh.is_some()
+    }
+
+    pub(crate) fn long_term_references(&mut self, num_frames: Option<u32>) {
+        self.ltr_num_frames = num_frames;
+    }
+
+    pub(crate) fn ltr_trust_mode(&mut self, enable: bool) {
+        self.ltr_trust_mode = if enable { 1 } else { 0 };
+    }
+
+    pub(crate) fn dpb_size(&mut self, dpb_size: u32) {
+        self.dpb_size = Some(dpb_size);
+    }
+
+    pub(crate) fn picture_type_decision(&mut self, enable: bool) {
+        self.picture_type_decision_enabled = if enable { 1 } else { 0 };
+    }
+
+    pub(crate) fn field_encoding(&mut self, enable: bool) {
+        self.field_encoding_enabled = enable;
+    }
+
+    /// Without picture type decision every frame is encoded in the order it is submitted, so
+    /// there is nothing to reorder B-frames with or to look ahead at.
+    pub(crate) fn is_picture_type_decision_valid(&self) -> bool {
+        self.picture_type_decision_enabled != 0
+            || (self.b_frames.unwrap_or(0) == 0 && self.lookahead_depth.is_none())
+    }
+
+    pub(crate) fn qp_map_mode(&mut self, qp_map_mode: Option<QpMapMode>) {
+        self.qp_map_mode = qp_map_mode;
+    }
+
+    /// NvEnc only reports slice offsets in synchronous mode.
+    pub(crate) fn is_slice_reporting_valid(&self) -> bool {
+        !(self.report_slice_offsets_enabled() && self.async_encode_enabled())
+    }
+
+    /// The HDR10 SEI messages that go into every frame.
+    pub(crate) fn hdr_sei_payloads(&self) -> Vec<SeiPayload> {
+        let mut payloads = Vec::new();
+        if let Some(mastering_display) = &self.mastering_display {
+            payloads.push(SeiPayload {
+                payload_type: MasteringDisplay::SEI_PAYLOAD_TYPE,
+                data: mastering_display.sei_payload(),
+            });
+        }
+        if let Some(content_light_level) = &self.content_light_level {
+            payloads.push(SeiPayload {
+                payload_type: ContentLightLevel::SEI_PAYLOAD_TYPE,
+                data: content_light_level.sei_payload(),
+            });
+        }
+        payloads
+    }
+
+    #[cfg(feature = "v12_0")]
+    pub(crate) fn av1_tiles(&mut self, columns: u32, rows: u32) {
+        self.av1_tiles = (columns, rows);
+    }
+
+    #[cfg(feature = "v12_0")]
+    pub(crate) fn annex_b(&mut self, enable: bool) {
+        self.annex_b_enabled = if enable { 1 } else { 0 };
+    }
+
     fn modify_init_params(&self, init_params: &mut crate::sys::NV_ENC_INITIALIZE_PARAMS) {
+        init_params.enableEncodeAsync = self.async_encode_enabled;
+        init_params.enablePTD = self.picture_type_decision_enabled;
+        init_params.set_reportSliceOffsets(self.report_slice_offsets);
+
         if let Some((frame_rate_num, frame_rate_den)) = self.filler_data_frame_rate {
             init_params.frameRateNum = frame_rate_num;
             init_params.frameRateDen = frame_rate_den;
@@ -275,22 +754,198 @@ impl ExtraOptions {
 
     fn modify_encode_config(&self, config: &mut crate::sys::NV_ENC_CONFIG) {
         config.rcParams.set_enableAQ(self.spatial_aq_enabled);
+        config.rcParams.set_aqStrength(self.aq_strength);
+        config
+            .rcParams
+            .set_enableTemporalAQ(self.temporal_aq_enabled);
+        if let Some(depth) = self.lookahead_depth {
+            config.rcParams.set_enableLookahead(1);
+            config.rcParams.lookaheadDepth = depth;
+            config.rcParams.set_disableIadapt(self.i_adapt_disabled);
+            config.rcParams.set_disableBadapt(self.b_adapt_disabled);
+        }
         config
             .rcParams
             .set_zeroReorderDelay(self.zero_reorder_delay_enabled);
-        config.rcParams.multiPass = self.multi_pass.into();
+        config.rcParams.multiPass = self.multi_pass.into_nvenc();
+
+        if let Some(gop_length) = self.gop_length {
+            config.gopLength = gop_length;
+        }
+        if let Some(b_frames) = self.b_frames {
+            config.frameIntervalP = b_frames as i32 + 1;
+        }
+        // The preset may have B-frames
+        if self.picture_type_decision_enabled == 0
+            || self.gop_length == Some(crate::sys::NVENC_INFINITE_GOPLENGTH)
+        {
+            config.frameIntervalP = 1;
+        }
+        if self.field_encoding_enabled {
+            config.frameFieldMode =
+                crate::sys::NV_ENC_PARAMS_FRAME_FIELD_MODE::NV_ENC_PARAMS_FRAME_FIELD_MODE_FIELD;
+        }
+
+        let rc_params = &mut config.rcParams;
+        if let Some(rate_control) = self.rate_control {
+            rc_params.rateControlMode = rate_control.into_nvenc();
+            match rate_control {
+                RateControl::ConstQp(qp) => rc_params.constQP = qp.into_nvenc(),
+                RateControl::Vbr { average, max } => {
+                    rc_params.averageBitRate = average;
+                    rc_params.maxBitRate = max;
+                }
+                RateControl::Cbr { bitrate } => {
+                    rc_params.averageBitRate = bitrate;
+                    rc_params.maxBitRate = bitrate;
+                }
+                RateControl::TargetQuality { cq, max } => {
+                    // The average has to be zero or the encoder targets it instead of the quality
+                    rc_params.averageBitRate = 0;
+                    rc_params.maxBitRate = max;
+                    rc_params.targetQuality = cq;
+                    rc_params.targetQualityLSB = 0;
+                }
+            }
+        }
+        if let Some((buffer_size, initial_delay)) = self.vbv {
+            rc_params.vbvBufferSize = buffer_size;
+            rc_params.vbvInitialDelay = initial_delay;
+        }
+        if let Some(min_qp) = self.min_qp {
+            rc_params.set_enableMinQP(1);
+            rc_params.minQP = min_qp.into_nvenc();
+        }
+        if let Some(max_qp) = self.max_qp {
+            rc_params.set_enableMaxQP(1);
+            rc_params.maxQP = max_qp.into_nvenc();
+        }
+        if let Some(initial_qp) = self.initial_qp {
+            rc_params.set_enableInitialRCQP(1);
+            rc_params.initialRCQP = initial_qp.into_nvenc();
+        }
+        if let Some(qp_map_mode) = self.qp_map_mode {
+            rc_params.qpMapMode = qp_map_mode.into_nvenc();
+        }
     }
 
     fn modify_h264_encode_config(&self, h264_config: &mut crate::sys::NV_ENC_CONFIG_H264) {
         h264_config.set_disableSPSPPS(self.inband_csd_disabled);
         h264_config.set_repeatSPSPPS(self.csd_should_repeat);
         h264_config.set_enableFillerDataInsertion(self.filler_data_enabled);
+        if let Some(idr_period) = self.idr_period_or_gop_length() {
+            h264_config.idrPeriod = idr_period;
+        }
+        if let Some(b_frame_ref_mode) = self.b_frame_ref_mode {
+            h264_config.useBFramesAsRef = b_frame_ref_mode.into_nvenc();
+        }
+        if let Some(level) = self.level {
+            h264_config.level = level.into_nvenc(Codec::H264);
+        }
+        if let Some(color_description) = &self.color_description {
+            set_color_description(&mut h264_config.h264VUIParameters, color_description);
+        }
+        if let Some(slice_mode) = self.slice_mode {
+            (h264_config.sliceMode, h264_config.sliceModeData) = slice_mode.mode_and_data();
+        }
+        if let Some((period, count)) = self.intra_refresh {
+            h264_config.set_enableIntraRefresh(1);
+            h264_config.intraRefreshPeriod = period;
+            h264_config.intraRefreshCnt = count;
+        }
+        if let Some(dpb_size) = self.dpb_size {
+            h264_config.maxNumRefFrames = dpb_size;
+        }
+        if let Some(num_frames) = self.ltr_num_frames {
+            h264_config.set_enableLTR(1);
+            h264_config.ltrNumFrames = num_frames;
+            h264_config.ltrTrustMode = self.ltr_trust_mode;
+        }
     }
 
     fn modify_hevc_encode_config(&self, hevc_config: &mut crate::sys::NV_ENC_CONFIG_HEVC) {
         hevc_config.set_disableSPSPPS(self.inband_csd_disabled);
         hevc_config.set_repeatSPSPPS(self.csd_should_repeat);
         hevc_config.set_enableFillerDataInsertion(self.filler_data_enabled);
+        if let Some(idr_period) = self.idr_period_or_gop_length() {
+            hevc_config.idrPeriod = idr_period;
+        }
+        if let Some(b_frame_ref_mode) = self.b_frame_ref_mode {
+            hevc_config.useBFramesAsRef = b_frame_ref_mode.into_nvenc();
+        }
+        if let Some(level) = self.level {
+            hevc_config.level = level.into_nvenc(Codec::Hevc);
+        }
+        if let Some(color_description) = &self.color_description {
+            set_color_description(&mut hevc_config.hevcVUIParameters, color_description);
+        }
+        if let Some(slice_mode) = self.slice_mode {
+            (hevc_config.sliceMode, hevc_config.sliceModeData) = slice_mode.mode_and_data();
+        }
+        if let Some((period, count)) = self.intra_refresh {
+            hevc_config.set_enableIntraRefresh(1);
+            hevc_config.intraRefreshPeriod = period;
+            hevc_config.intraRefreshCnt = count;
+        }
+        if let Some(dpb_size) = self.dpb_size {
+            hevc_config.maxNumRefFramesInDPB = dpb_size;
+        }
+        if let Some(num_frames) = self.ltr_num_frames {
+            hevc_config.set_enableLTR(1);
+            hevc_config.ltrNumFrames = num_frames;
+            hevc_config.ltrTrustMode = self.ltr_trust_mode;
+        }
+    }
+
+    #[cfg(feature = "v12_0")]
+    fn modify_av1_encode_config(&self, av1_config: &mut crate::sys::NV_ENC_CONFIG_AV1) {
+        av1_config.set_disableSeqHdr(self.inband_csd_disabled);
+        av1_config.set_repeatSeqHdr(self.csd_should_repeat);
+        av1_config.set_outputAnnexBFormat(self.annex_b_enabled);
+        // Uniform tiles, zero lets the encoder pick the smallest number allowed
+        (av1_config.numTileColumns, av1_config.numTileRows) = self.av1_tiles;
+        if let Some(idr_period) = self.idr_period_or_gop_length() {
+            av1_config.idrPeriod = idr_period;
+        }
+        if let Some(b_frame_ref_mode) = self.b_frame_ref_mode {
+            av1_config.useBFramesAsRef = b_frame_ref_mode.into_nvenc();
+        }
+        if let Some(level) = self.level {
+            av1_config.level = level.into_nvenc(Codec::Av1);
+        }
+    }
+}
+
+/// The HEVC VUI parameters are the same struct as the H.264 ones.
+fn set_color_description(
+    vui: &mut crate::sys::NV_ENC_CONFIG_H264_VUI_PARAMETERS,
+    color_description: &ColorDescription,
+) {
+    vui.videoSignalTypePresentFlag = 1;
+    vui.videoFullRangeFlag = if color_description.full_range { 1 } else { 0 };
+    vui.colourDescriptionPresentFlag = 1;
+
+    #[cfg(feature = "v12_0")]
+    {
+        vui.videoFormat = crate::sys::NV_ENC_VUI_VIDEO_FORMAT::NV_ENC_VUI_VIDEO_FORMAT_UNSPECIFIED;
+        vui.colourPrimaries = color_description.primaries.into_nvenc();
+        vui.transferCharacteristics = color_description.transfer_characteristics.into_nvenc();
+        vui.colourMatrix = color_description.matrix_coefficients.into_nvenc();
+    }
+    // The fields only became enums in 12.0, before that they hold the H.273 code points
+    #[cfg(not(feature = "v12_0"))]
+    {
+        // Unspecified
+        vui.videoFormat = 5;
+        vui.colourPrimaries = color_description.primaries as u32;
+        vui.transferCharacteristics = color_description.transfer_characteristics as u32;
+        vui.colourMatrix = color_description.matrix_coefficients as u32;
+    }
+
+    if let Some(chroma_sample_location) = color_description.chroma_sample_location {
+        vui.chromaSampleLocationFlag = 1;
+        vui.chromaSampleLocationTop = chroma_sample_location as u32;
+        vui.chromaSampleLocationBot = chroma_sample_location as u32;
     }
 }
 
@@ -0,0 +1,363 @@
+use crate::{
+    encoder::{
+        device::{DeviceImplTrait, IntoDevice},
+        library::Library,
+        texture::{frame_height_in_rows, plane_layout, TextureBufferImplTrait},
+    },
+    BufferFormat, NvEncError, Result,
+};
+use std::{
+    ffi::{c_int, c_uint, c_void},
+    sync::Arc,
+};
+
+/// Handle to a CUDA context (`CUcontext`).
+#[allow(non_camel_case_types)]
+pub type CUcontext = *mut c_void;
+/// Address in CUDA device memory (`CUdeviceptr`).
+#[allow(non_camel_case_types)]
+pub type CUdeviceptr = u64;
+
+#[allow(non_camel_case_types)]
+type CUresult = c_int;
+#[allow(non_camel_case_types)]
+type CUdevice = c_int;
+#[allow(non_camel_case_types)]
+type CUstream = *mut c_void;
+
+const CUDA_SUCCESS: CUresult = 0;
+const CU_MEMORYTYPE_DEVICE: c_uint = 2;
+
+/// Parameters for `cuMemcpy2DAsync`.
+#[repr(C)]
+#[allow(non_snake_case)]
+struct CUDA_MEMCPY2D {
+    srcXInBytes: usize,
+    srcY: usize,
+    srcMemoryType: c_uint,
+    srcHost: *const c_void,
+    srcDevice: CUdeviceptr,
+    srcArray: *mut c_void,
+    srcPitch: usize,
+    dstXInBytes: usize,
+    dstY: usize,
+    dstMemoryType: c_uint,
+    dstHost: *mut c_void,
+    dstDevice: CUdeviceptr,
+    dstArray: *mut c_void,
+    dstPitch: usize,
+    WidthInBytes: usize,
+    Height: usize,
+}
+
+/// The subset of the CUDA driver API used for staging input frames.
+struct CudaFunctions {
+    init: unsafe extern "C" fn(c_uint) -> CUresult,
+    device_get: unsafe extern "C" fn(*mut CUdevice, c_int) -> CUresult,
+    device_primary_ctx_retain: unsafe extern "C" fn(*mut CUcontext, CUdevice) -> CUresult,
+    device_primary_ctx_release: unsafe extern "C" fn(CUdevice) -> CUresult,
+    ctx_push_current: unsafe extern "C" fn(CUcontext) -> CUresult,
+    ctx_pop_current: unsafe extern "C" fn(*mut CUcontext) -> CUresult,
+    mem_alloc_pitch:
+        unsafe extern "C" fn(*mut CUdeviceptr, *mut usize, usize, usize, c_uint) -> CUresult,
+    mem_free: unsafe extern "C" fn(CUdeviceptr) -> CUresult,
+    memcpy_2d_async: unsafe extern "C" fn(*const CUDA_MEMCPY2D, CUstream) -> CUresult,
+    // Must outlive the function pointers above
+    _library: Library,
+}
+
+impl CudaFunctions {
+    fn load() -> Result<Self> {
+        let library = Library::load_cuda()?;
+        unsafe {
+            Ok(CudaFunctions {
+                init: load_fn(&library, "cuInit")?,
+                device_get: load_fn(&library, "cuDeviceGet")?,
+                device_primary_ctx_retain: load_fn(&library, "cuDevicePrimaryCtxRetain")?,
+                device_primary_ctx_release: load_fn(&library, "cuDevicePrimaryCtxRelease_v2")?,
+                ctx_push_current: load_fn(&library, "cuCtxPushCurrent_v2")?,
+                ctx_pop_current: load_fn(&library, "cuCtxPopCurrent_v2")?,
+                mem_alloc_pitch: load_fn(&library, "cuMemAllocPitch_v2")?,
+                mem_free: load_fn(&library, "cuMemFree_v2")?,
+                memcpy_2d_async: load_fn(&library, "cuMemcpy2DAsync_v2")?,
+                _library: library,
+            })
+        }
+    }
+}
+
+unsafe fn load_fn<T>(library: &Library, fn_name: &str) -> Result<T> {
+    library.fn_ptr(fn_name).ok_or(NvEncError::CudaLoadingFailed)
+}
+
+/// Converts a `CUresult` into a `Result`.
+fn check(result: CUresult) -> Result<()> {
+    if result == CUDA_SUCCESS {
+        Ok(())
+    } else {
+        Err(NvEncError::CudaCallFailed)
+    }
+}
+
+/// A CUDA context shared between the device and the texture buffers allocated from it.
+pub(crate) struct CudaContext {
+    functions: CudaFunctions,
+    context: CUcontext,
+    /// The device whose primary context was retained. `None` if the context is owned by the
+    /// caller.
+    primary_ctx_device: Option<CUdevice>,
+}
+
+// SAFETY: A `CUcontext` can be bound to any thread with `cuCtxPushCurrent` which is done before
+// every use.
+unsafe impl Send for CudaContext {}
+unsafe impl Sync for CudaContext {}
+
+impl Drop for CudaContext {
+    fn drop(&mut self) {
+        if let Some(device) = self.primary_ctx_device {
+            unsafe {
+                // Deliberately ignoring failure
+                let _ = (self.functions.device_primary_ctx_release)(device);
+            }
+        }
+    }
+}
+
+impl CudaContext {
+    /// Runs `f` with the context bound to the calling thread.
+    fn with_current<F, R>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce(&CudaFunctions) -> R,
+    {
+        unsafe {
+            check((self.functions.ctx_push_current)(self.context))?;
+            let result = f(&self.functions);
+            let mut popped = std::ptr::null_mut();
+            check((self.functions.ctx_pop_current)(&mut popped))?;
+            Ok(result)
+        }
+    }
+}
+
+/// An NvEnc device backed by a CUDA context.
+pub struct CudaDevice {
+    context: Arc<CudaContext>,
+}
+
+impl CudaDevice {
+    /// Create a device from the primary context of the GPU with the given ordinal. The primary
+    /// context is the one used by the CUDA runtime API.
+    pub fn new(ordinal: u32) -> Result<Self> {
+        let functions = CudaFunctions::load()?;
+        let mut device = 0;
+        let mut context = std::ptr::null_mut();
+        unsafe {
+            check((functions.init)(0))?;
+            check((functions.device_get)(&mut device, ordinal as c_int))?;
+            check((functions.device_primary_ctx_retain)(&mut context, device))?;
+        }
+        Ok(CudaDevice {
+            context: Arc::new(CudaContext {
+                functions,
+                context,
+                primary_ctx_device: Some(device),
+            }),
+        })
+    }
+
+    /// Create a device from an existing context, e.g. the one that a CUDA decoder is using.
+    ///
+    /// # Safety
+    ///
+    /// `context` must be a valid `CUcontext` that outlives both the `EncoderInput` and the
+    /// `EncoderOutput`.
+    pub unsafe fn from_context(context: CUcontext) -> Result<Self> {
+        let functions = CudaFunctions::load()?;
+        check((functions.init)(0))?;
+        Ok(CudaDevice {
+            context: Arc::new(CudaContext {
+                functions,
+                context,
+                primary_ctx_device: None,
+            }),
+        })
+    }
+}
+
+impl DeviceImplTrait for CudaDevice {
+    type Buffer = CudaTextureBuffer;
+    type Texture = CudaTexture;
+
+    fn device_type() -> crate::sys::NV_ENC_DEVICE_TYPE {
+        crate::sys::NV_ENC_DEVICE_TYPE::NV_ENC_DEVICE_TYPE_CUDA
+    }
+
+    fn as_ptr(&self) -> *mut c_void {
+        self.context.context
+    }
+
+    fn params_require_buffer_format() -> bool {
+        false
+    }
+
+    fn create_texture_buffer(
+        &self,
+        width: u32,
+        height: u32,
+        texture_format: <Self::Buffer as TextureBufferImplTrait>::TextureFormat,
+        buf_size: u32,
+    ) -> Result<Self::Buffer> {
+        // The luma plane is always the widest
+        let width_in_bytes = plane_layout(texture_format, width, height, 0)
+            .next()
+            .map(|plane| plane.width_in_bytes)
+            .unwrap_or_default();
+        let height_in_rows = frame_height_in_rows(texture_format, height);
+
+        let mut buffer = CudaTextureBuffer {
+            textures: Vec::with_capacity(buf_size as usize),
+            context: self.context.clone(),
+        };
+        self.context.with_current(|functions| {
+            for _ in 0..buf_size {
+                let mut device_ptr = 0;
+                let mut pitch = 0;
+                // Same element size as the Video Codec SDK samples
+                let result = unsafe {
+                    (functions.mem_alloc_pitch)(
+                        &mut device_ptr,
+                        &mut pitch,
+                        width_in_bytes,
+                        height_in_rows,
+                        16,
+                    )
+                };
+                if result != CUDA_SUCCESS {
+                    return Err(NvEncError::TextureBufferCreationFailed);
+                }
+                buffer.textures.push(CudaTexture {
+                    device_ptr,
+                    pitch,
+                    width,
+                    height,
+                    format: texture_format,
+                });
+            }
+            Ok(())
+        })??;
+        Ok(buffer)
+    }
+
+    fn copy_texture<T: AsRef<Self::Texture>>(
+        &self,
+        buffer: &Self::Buffer,
+        texture: T,
+        subresource_index: usize,
+    ) -> Result<()> {
+        let src = texture.as_ref();
+        let dst = &buffer.textures[subresource_index];
+        // The copy covers the planes of the buffer so it would read past a smaller texture
+        if src.format != dst.format || src.width < dst.width || src.height < dst.height {
+            return Err(NvEncError::InvalidTexture);
+        }
+        let src_planes = plane_layout(src.format, src.width, src.height, src.pitch);
+        let dst_planes = plane_layout(dst.format, dst.width, dst.height, dst.pitch);
+
+        self.context.with_current(|functions| {
+            for (src_plane, dst_plane) in src_planes.zip(dst_planes) {
+                let copy_params = CUDA_MEMCPY2D {
+                    srcXInBytes: 0,
+                    srcY: 0,
+                    srcMemoryType: CU_MEMORYTYPE_DEVICE,
+                    srcHost: std::ptr::null(),
+                    srcDevice: src.device_ptr + src_plane.offset as CUdeviceptr,
+                    srcArray: std::ptr::null_mut(),
+                    srcPitch: src_plane.pitch,
+                    dstXInBytes: 0,
+                    dstY: 0,
+                    dstMemoryType: CU_MEMORYTYPE_DEVICE,
+                    dstHost: std::ptr::null_mut(),
+                    dstDevice: dst.device_ptr + dst_plane.offset as CUdeviceptr,
+                    dstArray: std::ptr::null_mut(),
+                    dstPitch: dst_plane.pitch,
+                    WidthInBytes: dst_plane.width_in_bytes,
+                    Height: dst_plane.height,
+                };
+                // The NULL stream is used by NvEnc unless `NvEncSetIOCudaStreams` is called so the
+                // copy is guaranteed to finish before the encode starts
+                check(unsafe { (functions.memcpy_2d_async)(&copy_params, std::ptr::null_mut()) })?;
+            }
+            Ok(())
+        })?
+    }
+}
+
+impl IntoDevice for CudaDevice {
+    type Device = CudaDevice;
+
+    fn into_device(self) -> Self::Device {
+        self
+    }
+}
+
+/// A frame in CUDA device memory.
+pub struct CudaTexture {
+    pub(crate) device_ptr: CUdeviceptr,
+    pub(crate) pitch: usize,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) format: BufferFormat,
+}
+
+impl CudaTexture {
+    /// Describes a frame whose planes are stored one after the other, each starting at a multiple
+    /// of `pitch` like the frames output by NVDEC. `height` is the number of rows in the luma plane
+    /// and can be larger than the encode height to account for padded surfaces.
+    ///
+    /// # Safety
+    ///
+    /// `device_ptr` must point to an allocation in the encoder's CUDA context that is large enough
+    /// for the described frame.
+    pub unsafe fn new(
+        device_ptr: CUdeviceptr,
+        pitch: usize,
+        width: u32,
+        height: u32,
+        format: BufferFormat,
+    ) -> Self {
+        CudaTexture {
+            device_ptr,
+            pitch,
+            width,
+            height,
+            format,
+        }
+    }
+}
+
+impl AsRef<CudaTexture> for CudaTexture {
+    fn as_ref(&self) -> &CudaTexture {
+        self
+    }
+}
+
+/// Pitched allocations in CUDA device memory used for staging input frames.
+pub struct CudaTextureBuffer {
+    pub(crate) textures: Vec<CudaTexture>,
+    context: Arc<CudaContext>,
+}
+
+impl Drop for CudaTextureBuffer {
+    fn drop(&mut self) {
+        let textures = &self.textures;
+        // Deliberately ignoring failure
+        let _ = self.context.with_current(|functions| {
+            for texture in textures {
+                unsafe {
+                    let _ = (functions.mem_free)(texture.device_ptr);
+                }
+            }
+        });
+    }
+}
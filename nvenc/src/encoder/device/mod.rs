@@ -1,6 +1,8 @@
+mod cuda;
 #[cfg(windows)]
 mod windows;
 
+pub use self::cuda::*;
 #[cfg(windows)]
 pub use self::windows::*;
 
@@ -35,13 +37,14 @@ pub trait DeviceImplTrait {
         buf_size: u32,
     ) -> Result<Self::Buffer>;
 
-    /// Copy a texture to the given buffer.
+    /// Copy a texture to the given buffer. Returns `InvalidTexture` if the texture does not
+    /// match the buffer.
     fn copy_texture<T: AsRef<Self::Texture>>(
         &self,
         buffer: &Self::Buffer,
         texture: T,
         subresource_index: usize,
-    );
+    ) -> Result<()>;
 }
 
 pub trait IntoDevice {
@@ -77,7 +77,7 @@ impl DeviceImplTrait for DirectX11Device {
         buffer: &Self::Texture,
         texture: T,
         subresource_index: usize,
-    ) {
+    ) -> Result<()> {
         let d3d11_texture2d = texture.as_ref();
         // SAFETY: Windows API call
         unsafe {
@@ -92,6 +92,7 @@ impl DeviceImplTrait for DirectX11Device {
                 None,
             );
         }
+        Ok(())
     }
 }
 
@@ -0,0 +1,145 @@
+use crate::{NvEncError, Result};
+use std::collections::VecDeque;
+
+/// Follows which frames are in the encoder's DPB so reference frame invalidation can be checked
+/// before it is sent to the encoder. Assumes that every frame is a reference frame, which holds
+/// as long as there are no B-frames.
+pub(crate) struct DpbTracker {
+    size: usize,
+    idr_period: u32,
+    frames_since_idr: u32,
+    /// `(timestamp, is_valid)` of the frames in the DPB, oldest first.
+    frames: VecDeque<(u64, bool)>,
+}
+
+impl DpbTracker {
+    pub fn new(size: u32, idr_period: u32) -> Self {
+        DpbTracker {
+            size: size as usize,
+            idr_period,
+            frames_since_idr: 0,
+            frames: VecDeque::with_capacity(size as usize),
+        }
+    }
+
+    /// Record a frame that was submitted to the encoder. An IDR frame, either forced or from the
+    /// IDR period, empties the DPB.
+    pub fn push(&mut self, timestamp: u64, force_idr: bool) {
+        if force_idr || self.frames.is_empty() || self.frames_since_idr >= self.idr_period {
+            self.frames.clear();
+            self.frames_since_idr = 0;
+        }
+        if self.frames.len() == self.size {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((timestamp, true));
+        self.frames_since_idr += 1;
+    }
+
+    /// Returns the still valid frames that have to be invalidated so the encoder stops
+    /// referencing the frames with the given timestamps, i.e. those frames and every frame that
+    /// was encoded after them since they may have been predicted from them. Oldest first.
+    ///
+    /// Returns `ReferenceFrameNotInDpb` if a timestamp is not in the DPB and
+    /// `NoValidReferenceFrames` if no frame would be left to predict from. Nothing is marked
+    /// either way, see `mark_invalid`.
+    pub fn frames_to_invalidate(&self, timestamps: &[u64]) -> Result<Vec<u64>> {
+        let mut first_invalid = self.frames.len();
+        for &timestamp in timestamps {
+            let index = self
+                .frames
+                .iter()
+                .position(|&(frame_timestamp, _)| frame_timestamp == timestamp)
+                .ok_or(NvEncError::ReferenceFrameNotInDpb)?;
+            first_invalid = first_invalid.min(index);
+        }
+
+        if !self
+            .frames
+            .range(..first_invalid)
+            .any(|&(_, is_valid)| is_valid)
+        {
+            return Err(NvEncError::NoValidReferenceFrames);
+        }
+
+        Ok(self
+            .frames
+            .range(first_invalid..)
+            .filter(|&&(_, is_valid)| is_valid)
+            .map(|&(timestamp, _)| timestamp)
+            .collect())
+    }
+
+    /// Record that the encoder no longer references the frame with the given timestamp.
+    pub fn mark_invalid(&mut self, timestamp: u64) {
+        for (frame_timestamp, is_valid) in &mut self.frames {
+            if *frame_timestamp == timestamp {
+                *is_valid = false;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Marks what `frames_to_invalidate` returns, like `invalidate_reference_frames`.
+    fn invalidate(dpb: &mut DpbTracker, timestamps: &[u64]) -> Result<Vec<u64>> {
+        let frames = dpb.frames_to_invalidate(timestamps)?;
+        for &timestamp in &frames {
+            dpb.mark_invalid(timestamp);
+        }
+        Ok(frames)
+    }
+
+    #[test]
+    fn invalidation() {
+        let mut dpb = DpbTracker::new(4, 100);
+        for timestamp in 0..6 {
+            dpb.push(timestamp, false);
+        }
+
+        // Frames 0 and 1 have been pushed out of the DPB
+        assert!(matches!(
+            invalidate(&mut dpb, &[1]),
+            Err(NvEncError::ReferenceFrameNotInDpb)
+        ));
+        // Frame 5 may have been predicted from frame 4
+        assert_eq!(invalidate(&mut dpb, &[4]).unwrap(), [4, 5]);
+        // Only frames that are still valid are invalidated again
+        assert_eq!(invalidate(&mut dpb, &[3, 5]).unwrap(), [3]);
+        assert!(matches!(
+            invalidate(&mut dpb, &[2]),
+            Err(NvEncError::NoValidReferenceFrames)
+        ));
+        // The failed invalidation left frame 2 valid
+        assert_eq!(invalidate(&mut dpb, &[3]).unwrap(), []);
+
+        // A forced IDR frame starts over
+        dpb.push(6, true);
+        dpb.push(7, false);
+        assert!(matches!(
+            invalidate(&mut dpb, &[5]),
+            Err(NvEncError::ReferenceFrameNotInDpb)
+        ));
+        assert_eq!(invalidate(&mut dpb, &[7]).unwrap(), [7]);
+    }
+
+    #[test]
+    fn idr_period() {
+        let mut dpb = DpbTracker::new(4, 3);
+        for timestamp in 0..4 {
+            dpb.push(timestamp, false);
+        }
+        // Frame 3 is the second IDR frame
+        assert!(matches!(
+            dpb.frames_to_invalidate(&[2]),
+            Err(NvEncError::ReferenceFrameNotInDpb)
+        ));
+        assert!(matches!(
+            dpb.frames_to_invalidate(&[3]),
+            Err(NvEncError::NoValidReferenceFrames)
+        ));
+    }
+}
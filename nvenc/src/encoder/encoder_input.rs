@@ -1,25 +1,143 @@
 use super::{
+    buffer_items::InputResource,
     config::EncodeParams,
     device::DeviceImplTrait,
-    event::EventObjectTrait,
+    dpb::DpbTracker,
+    qp_map::{self, QpMap},
     raw_encoder::RawEncoder,
     shared::NvidiaEncoderWriter,
-    texture::{IntoNvEncBufferFormat, TextureBufferImplTrait},
+    texture::{plane_layout, IntoNvEncBufferFormat, TextureBufferImplTrait},
 };
-use crate::Result;
-use std::{mem::MaybeUninit, ops::Deref};
+use crate::{
+    error::NonZeroNvencStatus, BufferFormat, Codec, ForcedPictureType, NvEncError,
+    PictureStructure, Qp, QpMapMode, Result, SeiPayload,
+};
+use std::{ffi::c_void, mem::MaybeUninit, ops::Deref, ptr::NonNull};
+
+/// Where input frames are copied to before encoding.
+pub(crate) enum InputStaging<B> {
+    /// Textures on the device that are registered with the encoder.
+    Texture(B),
+    /// Input buffers allocated by NvEnc that are filled from host memory.
+    Host(BufferFormat),
+}
+
+/// A single plane of a frame in host memory.
+#[derive(Debug, Copy, Clone)]
+pub struct HostPlane<'a> {
+    pub data: &'a [u8],
+    /// Distance in bytes between the start of consecutive rows.
+    pub stride: usize,
+}
+
+/// Options for a single frame, passed to `EncoderInput::encode_frame_with_options` and
+/// `EncoderInput::encode_host_frame_with_options`. The default encodes the frame like
+/// `EncoderInput::encode_frame`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct EncodeFrameOptions {
+    /// Force the type of the picture. `None` follows the GOP structure.
+    pub picture_type: Option<ForcedPictureType>,
+    /// Duration of the frame in the units of the timestamp, returned as `outputDuration`. Zero
+    /// if unknown.
+    pub duration: u64,
+    /// Encode the frame with this QP instead of the configured one. Only with constant QP rate
+    /// control. Changing it reconfigures the encoder, so frames that the encoder is holding on
+    /// to for B-frames may be encoded with it too.
+    pub qp: Option<Qp>,
+    /// Write the codec parameters (SPS/PPS, and the VPS for HEVC) in front of the frame.
+    pub output_codec_params: bool,
+    /// Opaque data handed back in the `EncodedPacket` of the frame.
+    pub user_data: Option<Vec<u8>>,
+    /// Fields require `EncoderBuilder::field_encoding`.
+    pub picture_structure: PictureStructure,
+    /// SEI messages (H.264, HEVC) or metadata OBUs (AV1) inserted into the frame, after the
+    /// ones that go into every frame.
+    pub sei: Vec<SeiPayload>,
+    /// QP offsets or emphasis levels of the blocks of the frame. Requires
+    /// `EncoderBuilder::qp_map_mode`.
+    pub qp_map: Option<QpMap>,
+}
+
+/// Picks the picture types when picture type decision is disabled. Follows the GOP length and
+/// IDR period unless a type is forced.
+struct PictureTypeDecision {
+    gop_length: u32,
+    idr_period: u32,
+    /// `None` until the first frame, which has to be an IDR frame.
+    frames_since_idr: Option<u32>,
+}
+
+impl PictureTypeDecision {
+    fn new(gop_length: u32, idr_period: u32) -> Self {
+        PictureTypeDecision {
+            gop_length: gop_length.max(1),
+            idr_period: idr_period.max(1),
+            frames_since_idr: None,
+        }
+    }
+
+    /// The type of the next frame. Nothing changes until the frame is submitted and passed to
+    /// `submitted`, so a frame that fails does not use up its place in the GOP.
+    fn decide(&self, forced: Option<ForcedPictureType>) -> ForcedPictureType {
+        match (self.frames_since_idr, forced) {
+            (None, _) => ForcedPictureType::Idr,
+            (Some(_), Some(forced)) => forced,
+            (Some(frames), None) if frames >= self.idr_period => ForcedPictureType::Idr,
+            (Some(frames), None) if frames % self.gop_length == 0 => ForcedPictureType::Intra,
+            (Some(_), None) => ForcedPictureType::P,
+        }
+    }
+
+    fn submitted(&mut self, picture_type: ForcedPictureType) {
+        self.frames_since_idr = match (picture_type, self.frames_since_idr) {
+            (ForcedPictureType::Idr, _) | (_, None) => Some(1),
+            (_, Some(frames)) => Some(frames + 1),
+        };
+    }
+}
+
+/// The codec-specific part of `NV_ENC_PIC_PARAMS`. Only the variant of the current codec may be
+/// touched since they share the same memory.
+enum CodecPicParams<'a> {
+    H264(&'a mut crate::sys::NV_ENC_PIC_PARAMS_H264),
+    Hevc(&'a mut crate::sys::NV_ENC_PIC_PARAMS_HEVC),
+    Other,
+}
 
 pub struct EncoderInput<D: DeviceImplTrait> {
     device: D,
     writer: NvidiaEncoderWriter,
-    texture_buffer: <D as DeviceImplTrait>::Buffer,
+    staging: InputStaging<<D as DeviceImplTrait>::Buffer>,
     encode_params: EncodeParams,
+    codec: Codec,
+    /// `None` if reference frames cannot be invalidated because the DPB size is unknown or
+    /// there are B-frames.
+    dpb: Option<DpbTracker>,
+    /// `None` if the encoder decides the picture types.
+    picture_types: Option<PictureTypeDecision>,
+    /// The configured QP, `None` if the rate control is not constant QP.
+    const_qp: Option<Qp>,
+    /// QP the encoder was reconfigured with by `EncodeFrameOptions::qp`.
+    qp_override: Option<Qp>,
+    /// Whether frames can be submitted as two fields.
+    field_encoding: bool,
+    /// `None` if QP maps are ignored.
+    qp_map_mode: Option<QpMapMode>,
+    /// QP map of the frame that is being submitted. `encode_pic_params` points to it.
+    qp_map: Vec<i8>,
     encode_pic_params: crate::sys::NV_ENC_PIC_PARAMS,
+    /// SEI messages inserted into every frame.
+    persistent_sei: Vec<SeiPayload>,
+    /// SEI messages of the frame that is being submitted. They are kept until
+    /// `nvEncEncodePicture` returns.
+    frame_sei: Vec<SeiPayload>,
+    /// Point into `persistent_sei` followed by `frame_sei`. `encode_pic_params` points to them.
+    sei_payloads: Vec<crate::sys::NV_ENC_SEI_PAYLOAD>,
 }
 
 // SAFETY:
-// !Send is caused by the pointers in `encode_params` and `encode_pic_params` but those pointers
-// should be safe to move between threads.
+// !Send is caused by the pointers in `encode_params`, `encode_pic_params` and `sei_payloads` but
+// those pointers should be safe to move between threads.
 unsafe impl<D: DeviceImplTrait> Send for EncoderInput<D> {}
 
 impl<D: DeviceImplTrait> Drop for EncoderInput<D> {
@@ -32,30 +150,56 @@ impl<D: DeviceImplTrait> EncoderInput<D> {
     pub(crate) fn new(
         device: D,
         writer: NvidiaEncoderWriter,
-        texture_buffer: <D as DeviceImplTrait>::Buffer,
+        staging: InputStaging<<D as DeviceImplTrait>::Buffer>,
         encode_params: EncodeParams,
+        persistent_sei: Vec<SeiPayload>,
     ) -> Result<Self> {
         let encode_pic_params = {
             let mut tmp: crate::sys::NV_ENC_PIC_PARAMS =
                 unsafe { MaybeUninit::zeroed().assume_init() };
-            tmp.version = crate::sys::NV_ENC_PIC_PARAMS_VER;
+            tmp.version = writer.struct_versions().pic_params;
             tmp.inputWidth = encode_params.encode_width();
             tmp.inputHeight = encode_params.encode_height();
             tmp.inputPitch = tmp.inputWidth;
-            tmp.bufferFmt = texture_buffer.texture_format().into_nvenc_buffer_format();
+            tmp.bufferFmt = match &staging {
+                InputStaging::Texture(texture_buffer) => {
+                    texture_buffer.texture_format().into_nvenc_buffer_format()
+                }
+                InputStaging::Host(buffer_format) => buffer_format.into_nvenc_buffer_format(),
+            };
             tmp.pictureStruct = crate::sys::NV_ENC_PIC_STRUCT::NV_ENC_PIC_STRUCT_FRAME;
             tmp
         };
 
-        Ok(EncoderInput {
+        let mut encoder_input = EncoderInput {
             device,
             writer,
-            texture_buffer,
+            staging,
+            codec: encode_params.codec(),
+            dpb: encode_params
+                .dpb_size()
+                .filter(|_| !encode_params.has_b_frames())
+                .map(|dpb_size| DpbTracker::new(dpb_size, encode_params.idr_period())),
+            picture_types: (!encode_params.picture_type_decision_enabled()).then(|| {
+                PictureTypeDecision::new(encode_params.gop_length(), encode_params.idr_period())
+            }),
+            const_qp: encode_params.const_qp(),
+            qp_override: None,
+            field_encoding: encode_params.field_encoding_enabled(),
+            qp_map_mode: encode_params.qp_map_mode(),
+            qp_map: Vec::new(),
             encode_params,
             encode_pic_params,
-        })
+            persistent_sei,
+            frame_sei: Vec::new(),
+            sei_payloads: Vec::new(),
+        };
+        encoder_input.set_sei_payloads();
+        Ok(encoder_input)
     }
 
+    /// Change the bitrate of CBR or VBR rate control, and the VBV buffer size if given.
+    /// Returns `UnsupportedSetting` for `RateControl::ConstQp` and `RateControl::TargetQuality`.
     pub fn update_average_bitrate(
         &mut self,
         bitrate: u32,
@@ -71,7 +215,7 @@ impl<D: DeviceImplTrait> EncoderInput<D> {
         unsafe {
             let mut sequence_param_payload: crate::sys::NV_ENC_SEQUENCE_PARAM_PAYLOAD =
                 MaybeUninit::zeroed().assume_init();
-            sequence_param_payload.version = crate::sys::NV_ENC_SEQUENCE_PARAM_PAYLOAD_VER;
+            sequence_param_payload.version = self.writer.struct_versions().sequence_param_payload;
             sequence_param_payload.inBufferSize = buffer.len() as u32;
             sequence_param_payload.spsppsBuffer = buffer.as_mut_ptr().cast();
             sequence_param_payload.outSPSPPSPayloadSize = &mut bytes_written;
@@ -87,33 +231,259 @@ impl<D: DeviceImplTrait> EncoderInput<D> {
     where
         T: AsRef<D::Texture>,
     {
-        self.writer.write(|index, buffer| {
-            self.device
-                .copy_texture(&self.texture_buffer, texture, index);
+        self.encode_frame_with_options(texture, timestamp, EncodeFrameOptions::default())
+    }
 
-            buffer.mapped_input =
-                map_input(self.writer.deref(), buffer.registered_resource.as_ptr())?;
+    pub fn encode_frame_with_options<T>(
+        &mut self,
+        texture: T,
+        timestamp: u64,
+        mut options: EncodeFrameOptions,
+    ) -> Result<()>
+    where
+        T: AsRef<D::Texture>,
+    {
+        if let InputStaging::Host(_) = self.staging {
+            return Err(NvEncError::InputModeMismatch);
+        }
+        self.validate_frame_options(&options)?;
+        self.override_qp(options.qp)?;
+        let user_data = options.user_data.take();
+
+        self.writer.prepare(|index, buffer| {
+            let texture_buffer = match &self.staging {
+                InputStaging::Texture(texture_buffer) => texture_buffer,
+                InputStaging::Host(_) => return Err(NvEncError::InputModeMismatch),
+            };
+            let registered_resource = match buffer.input_resource {
+                InputResource::Registered(registered_resource) => registered_resource,
+                InputResource::HostBuffer(_) => return Err(NvEncError::InputModeMismatch),
+            };
+            // Not mapped until `map_input` succeeds, see `discard_prepared`
+            buffer.mapped_input = std::ptr::null_mut();
+
+            self.device.copy_texture(texture_buffer, texture, index)?;
+
+            buffer.mapped_input = map_input(self.writer.deref(), registered_resource.as_ptr())?;
             self.encode_pic_params.inputBuffer = buffer.mapped_input;
             self.encode_pic_params.outputBitstream = buffer.output_buffer.as_ptr();
-            self.encode_pic_params.completionEvent = buffer.event_obj.as_ptr();
+            self.encode_pic_params.completionEvent = buffer.completion_event();
+            *buffer.user_data.get_mut() = user_data;
             Ok(())
         })?;
 
+        self.submit_frame(timestamp, &mut options)
+    }
+
+    /// Encode a frame from host memory. Only available if the encoder was built with
+    /// `EncoderBuilder::build_with_host_input`.
+    ///
+    /// `planes` follow the order of the buffer format: Y then UV for NV12 and P010, Y then V then
+    /// U for YV12, Y then U then V for IYUV and YUV444, and a single plane for the packed RGB
+    /// formats. Each plane can have its own stride.
+    pub fn encode_host_frame(&mut self, planes: &[HostPlane<'_>], timestamp: u64) -> Result<()> {
+        self.encode_host_frame_with_options(planes, timestamp, EncodeFrameOptions::default())
+    }
+
+    pub fn encode_host_frame_with_options(
+        &mut self,
+        planes: &[HostPlane<'_>],
+        timestamp: u64,
+        mut options: EncodeFrameOptions,
+    ) -> Result<()> {
+        let buffer_format = match self.staging {
+            InputStaging::Host(buffer_format) => buffer_format,
+            InputStaging::Texture(_) => return Err(NvEncError::InputModeMismatch),
+        };
+        let width = self.encode_params.encode_width();
+        let height = self.encode_params.encode_height();
+        validate_host_planes(buffer_format, width, height, planes)?;
+        self.validate_frame_options(&options)?;
+        self.override_qp(options.qp)?;
+        let user_data = options.user_data.take();
+
+        self.writer.prepare(|_, buffer| {
+            let input_buffer = match buffer.input_resource {
+                InputResource::HostBuffer(input_buffer) => input_buffer,
+                InputResource::Registered(_) => return Err(NvEncError::InputModeMismatch),
+            };
+
+            let pitch = copy_to_input_buffer(
+                self.writer.deref(),
+                input_buffer,
+                buffer_format,
+                width,
+                height,
+                planes,
+            )?;
+
+            buffer.mapped_input = input_buffer.as_ptr();
+            self.encode_pic_params.inputBuffer = buffer.mapped_input;
+            self.encode_pic_params.inputPitch = pitch;
+            self.encode_pic_params.outputBitstream = buffer.output_buffer.as_ptr();
+            self.encode_pic_params.completionEvent = buffer.completion_event();
+            *buffer.user_data.get_mut() = user_data;
+            Ok(())
+        })?;
+
+        self.submit_frame(timestamp, &mut options)
+    }
+
+    /// Checks the options before a buffer is written so that nothing is left half submitted.
+    fn validate_frame_options(&self, options: &EncodeFrameOptions) -> Result<()> {
+        let needs_client_picture_types = matches!(
+            options.picture_type,
+            Some(ForcedPictureType::P | ForcedPictureType::Skipped)
+        );
+        if (needs_client_picture_types && self.picture_types.is_none())
+            || (options.qp.is_some() && self.const_qp.is_none())
+            || (options.picture_structure != PictureStructure::Frame && !self.field_encoding)
+            || (!options.sei.is_empty()
+                && !matches!(self.codec, Codec::H264 | Codec::Hevc | Codec::Av1))
+            || (self.codec == Codec::Av1
+                && options
+                    .sei
+                    .iter()
+                    .any(|sei| sei.payload_type == SeiPayload::USER_DATA_UNREGISTERED))
+            || (self.codec != Codec::Hevc
+                && options
+                    .sei
+                    .iter()
+                    .any(|sei| sei.payload_type == SeiPayload::TIME_CODE))
+        {
+            return Err(NvEncError::UnsupportedSetting);
+        }
+        if let Some(qp_map) = &options.qp_map {
+            let qp_map_mode = self.qp_map_mode.ok_or(NvEncError::UnsupportedSetting)?;
+            qp_map.validate(self.qp_map_dimensions(), qp_map_mode == QpMapMode::Emphasis)?;
+        }
+        Ok(())
+    }
+
+    /// Reconfigures the encoder if the QP of the frame differs from the previous one. Done before
+    /// a buffer is written since the encoder keeps using the previous QP if this fails.
+    fn override_qp(&mut self, qp: Option<Qp>) -> Result<()> {
+        if qp != self.qp_override {
+            if let Some(qp) = qp.or(self.const_qp) {
+                self.encode_params.set_const_qp(&self.writer, qp)?;
+            }
+            self.qp_override = qp;
+        }
+        Ok(())
+    }
+
+    /// Width and height in blocks of the `QpMap::Blocks` of a frame.
+    pub fn qp_map_dimensions(&self) -> (u32, u32) {
+        qp_map::dimensions(
+            self.codec,
+            self.encode_params.encode_width(),
+            self.encode_params.encode_height(),
+        )
+    }
+
+    fn submit_frame(&mut self, timestamp: u64, options: &mut EncodeFrameOptions) -> Result<()> {
+        use crate::sys::NV_ENC_PIC_FLAGS;
+
+        // Only the flags of `force_idr_on_next` are kept if the frame fails
+        let previous_flags = self.encode_pic_params.encodePicFlags;
         // Used for invalidation of frames
         self.encode_pic_params.inputTimeStamp = timestamp;
+        self.encode_pic_params.inputDuration = options.duration;
+        self.encode_pic_params.pictureStruct = options.picture_structure.into_nvenc();
+        if options.output_codec_params {
+            self.encode_pic_params.encodePicFlags |=
+                NV_ENC_PIC_FLAGS::NV_ENC_PIC_FLAG_OUTPUT_SPSPPS as u32;
+        }
+        let flag_idr = self.encode_pic_params.encodePicFlags
+            & NV_ENC_PIC_FLAGS::NV_ENC_PIC_FLAG_FORCEIDR as u32
+            != 0;
+        if let Some(qp_map) = options.qp_map.take() {
+            let dimensions = self.qp_map_dimensions();
+            qp_map.write_to(&mut self.qp_map, self.codec, dimensions);
+            self.encode_pic_params.qpDeltaMap = self.qp_map.as_mut_ptr();
+            self.encode_pic_params.qpDeltaMapSize = self.qp_map.len() as u32;
+        }
+        if !options.sei.is_empty() {
+            self.frame_sei = std::mem::take(&mut options.sei);
+            self.set_sei_payloads();
+        }
+        let decided_picture_type = self.picture_types.as_ref().map(|picture_types| {
+            let forced = if flag_idr {
+                Some(ForcedPictureType::Idr)
+            } else {
+                options.picture_type
+            };
+            picture_types.decide(forced)
+        });
+        let is_forced_idr = match decided_picture_type {
+            Some(picture_type) => {
+                self.encode_pic_params.pictureType = picture_type.into_nvenc();
+                picture_type == ForcedPictureType::Idr
+            }
+            None => {
+                match options.picture_type {
+                    Some(ForcedPictureType::Intra) => {
+                        self.encode_pic_params.encodePicFlags |=
+                            NV_ENC_PIC_FLAGS::NV_ENC_PIC_FLAG_FORCEINTRA as u32;
+                    }
+                    Some(ForcedPictureType::Idr) => {
+                        self.encode_pic_params.encodePicFlags |=
+                            NV_ENC_PIC_FLAGS::NV_ENC_PIC_FLAG_FORCEIDR as u32;
+                    }
+                    // Checked by `validate_frame_options`
+                    Some(ForcedPictureType::P | ForcedPictureType::Skipped) | None => {}
+                }
+                flag_idr || options.picture_type == Some(ForcedPictureType::Idr)
+            }
+        };
 
-        unsafe {
-            self.writer.encode_picture(&mut self.encode_pic_params)?;
+        let result = self.encode_picture();
+        self.encode_pic_params.qpDeltaMap = std::ptr::null_mut();
+        self.encode_pic_params.qpDeltaMapSize = 0;
+        if !self.frame_sei.is_empty() {
+            self.frame_sei.clear();
+            self.set_sei_payloads();
+        }
+        if result.is_err() {
+            self.encode_pic_params.encodePicFlags = previous_flags;
+        }
+        result?;
+
+        if let (Some(picture_types), Some(picture_type)) =
+            (&mut self.picture_types, decided_picture_type)
+        {
+            picture_types.submitted(picture_type);
+        }
+        if let Some(dpb) = &mut self.dpb {
+            dpb.push(timestamp, is_forced_idr);
         }
 
         // The flags are only good for one frame so we reset them after encoding
         self.encode_pic_params.encodePicFlags = 0;
+        match self.codec_pic_params() {
+            CodecPicParams::H264(h264_pic_params) => {
+                h264_pic_params.forceIntraRefreshWithFrameCnt = 0;
+                h264_pic_params.set_ltrMarkFrame(0);
+                h264_pic_params.ltrMarkFrameIdx = 0;
+                h264_pic_params.set_ltrUseFrames(0);
+                h264_pic_params.ltrUseFrameBitmap = 0;
+            }
+            CodecPicParams::Hevc(hevc_pic_params) => {
+                hevc_pic_params.forceIntraRefreshWithFrameCnt = 0;
+                hevc_pic_params.set_ltrMarkFrame(0);
+                hevc_pic_params.ltrMarkFrameIdx = 0;
+                hevc_pic_params.set_ltrUseFrames(0);
+                hevc_pic_params.ltrUseFrameBitmap = 0;
+            }
+            CodecPicParams::Other => {}
+        }
 
         Ok(())
     }
 
     /// Force the next frame to be encoded as an IDR picture and also emits codec parameters
-    /// (SPS/PPS) inline in the bitstream.
+    /// (SPS/PPS) inline in the bitstream. Same as encoding it with `ForcedPictureType::Idr` and
+    /// `output_codec_params` in its `EncodeFrameOptions`.
     #[inline]
     pub fn force_idr_on_next(&mut self) {
         self.encode_pic_params.encodePicFlags =
@@ -121,23 +491,198 @@ impl<D: DeviceImplTrait> EncoderInput<D> {
                 | crate::sys::NV_ENC_PIC_FLAGS::NV_ENC_PIC_FLAG_OUTPUT_SPSPPS as u32;
     }
 
+    /// Stop the encoder from predicting from the frames with the given timestamps, e.g. because
+    /// they were lost in transit, and from every frame encoded after them. Requires the DPB size
+    /// to be set with `EncoderBuilder::dpb_size` and no B-frames, otherwise returns
+    /// `UnsupportedSetting`.
+    ///
+    /// Returns `ReferenceFrameNotInDpb` if a frame has already left the DPB and
+    /// `NoValidReferenceFrames` if no frame would be left to predict from. The decoder cannot
+    /// recover without an IDR frame in both cases, see `force_idr_on_next`.
+    pub fn invalidate_reference_frames(&mut self, timestamps: &[u64]) -> Result<()> {
+        let dpb = self.dpb.as_mut().ok_or(NvEncError::UnsupportedSetting)?;
+        for timestamp in dpb.frames_to_invalidate(timestamps)? {
+            unsafe {
+                self.writer.invalidate_ref_frames(timestamp)?;
+            }
+            dpb.mark_invalid(timestamp);
+        }
+        Ok(())
+    }
+
+    /// Start a new intra refresh wave with the next frame instead of encoding an IDR picture.
+    /// Returns `UnsupportedSetting` if intra refresh was not enabled with
+    /// `EncoderBuilder::intra_refresh`.
+    pub fn start_intra_refresh_on_next(&mut self) -> Result<()> {
+        let count = self
+            .encode_params
+            .intra_refresh_count()
+            .ok_or(NvEncError::UnsupportedSetting)?;
+        match self.codec_pic_params() {
+            CodecPicParams::H264(h264_pic_params) => {
+                h264_pic_params.forceIntraRefreshWithFrameCnt = count;
+            }
+            CodecPicParams::Hevc(hevc_pic_params) => {
+                hevc_pic_params.forceIntraRefreshWithFrameCnt = count;
+            }
+            CodecPicParams::Other => return Err(NvEncError::UnsupportedSetting),
+        }
+        Ok(())
+    }
+
+    /// Mark the next frame as long-term reference `index`, replacing the frame that was marked
+    /// with it before. Returns `UnsupportedSetting` if LTR was not enabled with
+    /// `EncoderBuilder::long_term_references` or is in trust mode.
+    pub fn mark_ltr_on_next(&mut self, index: u32) -> Result<()> {
+        let num_frames = self.per_picture_ltr_frames()?;
+        if index >= num_frames {
+            return Err(NvEncError::InvalidLtrIndex);
+        }
+        match self.codec_pic_params() {
+            CodecPicParams::H264(h264_pic_params) => {
+                h264_pic_params.set_ltrMarkFrame(1);
+                h264_pic_params.ltrMarkFrameIdx = index;
+            }
+            CodecPicParams::Hevc(hevc_pic_params) => {
+                hevc_pic_params.set_ltrMarkFrame(1);
+                hevc_pic_params.ltrMarkFrameIdx = index;
+            }
+            CodecPicParams::Other => return Err(NvEncError::UnsupportedSetting),
+        }
+        Ok(())
+    }
+
+    /// Make the next frame reference the long-term references whose bits are set in `bitmap`,
+    /// e.g. the last one the receiver acknowledged after packet loss. Other references are not
+    /// used by it or by the frames that follow it.
+    pub fn use_ltr_on_next(&mut self, bitmap: u32) -> Result<()> {
+        let num_frames = self.per_picture_ltr_frames()?;
+        if bitmap == 0 || bitmap.checked_shr(num_frames).unwrap_or(0) != 0 {
+            return Err(NvEncError::InvalidLtrIndex);
+        }
+        match self.codec_pic_params() {
+            CodecPicParams::H264(h264_pic_params) => {
+                h264_pic_params.set_ltrUseFrames(1);
+                h264_pic_params.ltrUseFrameBitmap = bitmap;
+            }
+            CodecPicParams::Hevc(hevc_pic_params) => {
+                hevc_pic_params.set_ltrUseFrames(1);
+                hevc_pic_params.ltrUseFrameBitmap = bitmap;
+            }
+            CodecPicParams::Other => return Err(NvEncError::UnsupportedSetting),
+        }
+        Ok(())
+    }
+
+    /// Number of long-term references that are marked by the client.
+    fn per_picture_ltr_frames(&self) -> Result<u32> {
+        match self.encode_params.ltr_config() {
+            Some((num_frames, false)) => Ok(num_frames),
+            _ => Err(NvEncError::UnsupportedSetting),
+        }
+    }
+
+    /// Points `encode_pic_params` to the SEI messages of every frame and of the next frame.
+    fn set_sei_payloads(&mut self) {
+        self.sei_payloads.clear();
+        self.sei_payloads.extend(
+            self.persistent_sei
+                .iter_mut()
+                .chain(&mut self.frame_sei)
+                .map(|sei| crate::sys::NV_ENC_SEI_PAYLOAD {
+                    payloadSize: sei.data.len() as u32,
+                    payloadType: sei.payload_type,
+                    payload: sei.data.as_mut_ptr(),
+                }),
+        );
+
+        let count = self.sei_payloads.len() as u32;
+        let payloads = if count == 0 {
+            std::ptr::null_mut()
+        } else {
+            self.sei_payloads.as_mut_ptr()
+        };
+        let codec_pic_params = &mut self.encode_pic_params.codecPicParams;
+        match self.codec {
+            Codec::H264 => {
+                let h264_pic_params = unsafe { codec_pic_params.h264PicParams.as_mut() };
+                h264_pic_params.seiPayloadArrayCnt = count;
+                h264_pic_params.seiPayloadArray = payloads;
+            }
+            Codec::Hevc => {
+                let hevc_pic_params = unsafe { codec_pic_params.hevcPicParams.as_mut() };
+                hevc_pic_params.seiPayloadArrayCnt = count;
+                hevc_pic_params.seiPayloadArray = payloads;
+            }
+            #[cfg(feature = "v12_0")]
+            Codec::Av1 => {
+                let av1_pic_params = unsafe { codec_pic_params.av1PicParams.as_mut() };
+                av1_pic_params.obuPayloadArrayCnt = count;
+                av1_pic_params.obuPayloadArray = payloads;
+            }
+            _ => {}
+        }
+    }
+
+    fn codec_pic_params(&mut self) -> CodecPicParams<'_> {
+        let codec_pic_params = &mut self.encode_pic_params.codecPicParams;
+        match self.codec {
+            Codec::H264 => CodecPicParams::H264(unsafe { codec_pic_params.h264PicParams.as_mut() }),
+            Codec::Hevc => CodecPicParams::Hevc(unsafe { codec_pic_params.hevcPicParams.as_mut() }),
+            _ => CodecPicParams::Other,
+        }
+    }
+
     fn end_encode(&mut self) -> Result<()> {
-        self.writer.write(|_, buffer| {
+        self.writer.prepare(|_, buffer| {
             buffer.end_of_stream = true;
             self.encode_pic_params.inputBuffer = std::ptr::null_mut();
             self.encode_pic_params.outputBitstream = std::ptr::null_mut();
-            self.encode_pic_params.completionEvent = buffer.event_obj.as_ptr();
+            self.encode_pic_params.completionEvent = buffer.completion_event();
             self.encode_pic_params.encodePicFlags =
                 crate::sys::NV_ENC_PIC_FLAGS::NV_ENC_PIC_FLAG_EOS as u32;
             Ok(())
         })?;
 
-        unsafe {
-            self.writer.encode_picture(&mut self.encode_pic_params)?;
-        }
+        self.encode_picture()
+    }
 
+    /// Submits the picture described by `encode_pic_params` for the item that was just prepared
+    /// and hands the item to the reader. If the encoder does not accept the picture, the item is
+    /// discarded instead so the reader does not wait for an output that never comes.
+    fn encode_picture(&mut self) -> Result<()> {
+        match unsafe { self.writer.encode_picture(&mut self.encode_pic_params) } {
+            Ok(()) => {
+                self.writer.commit();
+                // Every frame submitted so far can be output
+                self.writer.set_outputs_ready();
+            }
+            // The encoder is holding on to the frame to use it as a backward reference for B-frames.
+            // Its output becomes available once a later call succeeds.
+            Err(NvEncError::Sys(NonZeroNvencStatus::NV_ENC_ERR_NEED_MORE_INPUT)) => {
+                self.writer.commit();
+            }
+            Err(err) => {
+                self.discard_prepared();
+                return Err(err);
+            }
+        }
         Ok(())
     }
+
+    /// Undoes what was prepared for a picture that was not submitted. The item is not handed to
+    /// the reader and is reused by the next frame.
+    fn discard_prepared(&mut self) {
+        self.writer.prepare(|_, buffer| {
+            if !buffer.mapped_input.is_null() {
+                // TODO: Prob should log the error instead of ignoring it.
+                let _ = buffer.unmap_input(&self.writer);
+                buffer.mapped_input = std::ptr::null_mut();
+            }
+            buffer.end_of_stream = false;
+            *buffer.user_data.get_mut() = None;
+        });
+    }
 }
 
 /// Helper function for creating a `NV_ENC_MAP_INPUT_RESOURCE` from a `NV_ENC_REGISTERED_PTR`.
@@ -147,7 +692,7 @@ fn map_input(
 ) -> Result<crate::sys::NV_ENC_INPUT_PTR> {
     let mut map_input_resource_params: crate::sys::NV_ENC_MAP_INPUT_RESOURCE =
         unsafe { MaybeUninit::zeroed().assume_init() };
-    map_input_resource_params.version = crate::sys::NV_ENC_MAP_INPUT_RESOURCE_VER;
+    map_input_resource_params.version = raw_encoder.struct_versions().map_input_resource;
     map_input_resource_params.registeredResource = registered_resource;
 
     unsafe {
@@ -155,3 +700,143 @@ fn map_input(
     }
     Ok(map_input_resource_params.mappedResource)
 }
+
+/// Checks that `planes` hold a whole frame of the given format and dimensions.
+fn validate_host_planes(
+    buffer_format: BufferFormat,
+    width: u32,
+    height: u32,
+    planes: &[HostPlane<'_>],
+) -> Result<()> {
+    if plane_layout(buffer_format, width, height, 0).count() != planes.len() {
+        return Err(NvEncError::InvalidHostFrame);
+    }
+
+    for (plane, host_plane) in plane_layout(buffer_format, width, height, 0).zip(planes) {
+        if plane.height == 0 {
+            continue;
+        }
+        let required_len = host_plane.stride * (plane.height - 1) + plane.width_in_bytes;
+        if host_plane.stride < plane.width_in_bytes || host_plane.data.len() < required_len {
+            return Err(NvEncError::InvalidHostFrame);
+        }
+    }
+    Ok(())
+}
+
+/// Copies `planes` row by row into a locked input buffer. Returns the pitch of the input buffer.
+fn copy_to_input_buffer(
+    raw_encoder: &RawEncoder,
+    input_buffer: NonNull<c_void>,
+    buffer_format: BufferFormat,
+    width: u32,
+    height: u32,
+    planes: &[HostPlane<'_>],
+) -> Result<u32> {
+    let mut lock_input_buffer_params: crate::sys::NV_ENC_LOCK_INPUT_BUFFER =
+        unsafe { MaybeUninit::zeroed().assume_init() };
+    lock_input_buffer_params.version = raw_encoder.struct_versions().lock_input_buffer;
+    lock_input_buffer_params.inputBuffer = input_buffer.as_ptr();
+
+    unsafe {
+        raw_encoder.lock_input_buffer(&mut lock_input_buffer_params)?;
+    }
+
+    let pitch = lock_input_buffer_params.pitch;
+    let dst: *mut u8 = lock_input_buffer_params.bufferDataPtr.cast();
+    for (plane, host_plane) in
+        plane_layout(buffer_format, width, height, pitch as usize).zip(planes)
+    {
+        for row in 0..plane.height {
+            let src_row = &host_plane.data[row * host_plane.stride..][..plane.width_in_bytes];
+            // SAFETY: The locked buffer is at least as large as the layout of a frame with the
+            // locked pitch, and `validate_host_planes` checked the bounds of `src_row`.
+            unsafe {
+                std::ptr::copy_nonoverlapping(
+                    src_row.as_ptr(),
+                    dst.add(plane.offset + row * plane.pitch),
+                    plane.width_in_bytes,
+                );
+            }
+        }
+    }
+
+    unsafe {
+        raw_encoder.unlock_input_buffer(input_buffer.as_ptr())?;
+    }
+    Ok(pitch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn host_planes_validation() {
+        let luma = vec![0; 64 * 3 + 60];
+        let chroma = vec![0; 64 + 60];
+        let planes = [
+            HostPlane {
+                data: &luma,
+                stride: 64,
+            },
+            HostPlane {
+                data: &chroma,
+                stride: 64,
+            },
+        ];
+        assert!(validate_host_planes(BufferFormat::Nv12, 60, 4, &planes).is_ok());
+        // Missing a plane
+        assert!(validate_host_planes(BufferFormat::Nv12, 60, 4, &planes[..1]).is_err());
+        // Last row of the luma plane is cut short
+        assert!(validate_host_planes(BufferFormat::Nv12, 61, 4, &planes).is_err());
+        // Stride smaller than a row
+        assert!(validate_host_planes(
+            BufferFormat::Nv12,
+            60,
+            2,
+            &[
+                planes[0],
+                HostPlane {
+                    data: &chroma,
+                    stride: 32,
+                }
+            ]
+        )
+        .is_err());
+    }
+
+    fn next(
+        picture_types: &mut PictureTypeDecision,
+        forced: Option<ForcedPictureType>,
+    ) -> ForcedPictureType {
+        let picture_type = picture_types.decide(forced);
+        picture_types.submitted(picture_type);
+        picture_type
+    }
+
+    #[test]
+    fn client_picture_types() {
+        use ForcedPictureType::*;
+
+        let mut picture_types = PictureTypeDecision::new(3, 6);
+        let decided: Vec<_> = (0..8).map(|_| next(&mut picture_types, None)).collect();
+        assert_eq!(decided, [Idr, P, P, Intra, P, P, Idr, P]);
+
+        // A forced IDR frame restarts the GOP and the IDR period
+        assert_eq!(next(&mut picture_types, Some(Idr)), Idr);
+        assert_eq!(next(&mut picture_types, Some(Skipped)), Skipped);
+        assert_eq!(next(&mut picture_types, None), P);
+        assert_eq!(next(&mut picture_types, None), Intra);
+
+        // The first frame is always an IDR frame
+        let mut picture_types = PictureTypeDecision::new(3, 6);
+        assert_eq!(next(&mut picture_types, Some(P)), Idr);
+
+        // A first frame that fails to submit does not use up the IDR frame
+        let mut picture_types = PictureTypeDecision::new(3, 6);
+        assert_eq!(picture_types.decide(None), Idr);
+        assert_eq!(next(&mut picture_types, None), Idr);
+        assert_eq!(next(&mut picture_types, None), P);
+    }
+}
@@ -1,22 +1,49 @@
-use super::{event::EventObjectTrait, shared::NvidiaEncoderReader};
+use super::{
+    event::EventObjectTrait,
+    packet::{EncodedPacket, OwnedEncodedPacket},
+    shared::NvidiaEncoderReader,
+    stats::{SessionStats, SessionStatsCollector},
+};
 use crate::{NvEncError, Result};
-use std::mem::MaybeUninit;
+use std::{cell::RefCell, mem::MaybeUninit, time::Instant};
 
 pub struct EncoderOutput {
     reader: NvidiaEncoderReader,
+    session_stats: RefCell<SessionStatsCollector>,
 }
 
 impl EncoderOutput {
     pub(crate) fn new(reader: NvidiaEncoderReader) -> Self {
-        EncoderOutput { reader }
+        EncoderOutput {
+            reader,
+            session_stats: RefCell::new(SessionStatsCollector::default()),
+        }
     }
 
-    pub fn wait_for_output<F: FnMut(&crate::sys::NV_ENC_LOCK_BITSTREAM) -> ()>(
+    /// Statistics of every frame that has been read since the encoder was created or the
+    /// statistics were reset.
+    pub fn session_stats(&self) -> SessionStats {
+        self.session_stats.borrow().stats()
+    }
+
+    pub fn reset_session_stats(&self) {
+        *self.session_stats.borrow_mut() = SessionStatsCollector::default();
+    }
+
+    /// Wait for the output of the next frame and pass it to `consume_output`. The packet borrows
+    /// the output buffer, which is handed back to the encoder once `consume_output` returns.
+    pub fn wait_for_output<F: FnMut(EncodedPacket<'_>)>(
         &self,
         mut consume_output: F,
     ) -> Result<()> {
         self.reader.read(|buffer| -> Result<()> {
-            buffer.event_obj.wait()?;
+            // Frames held back for B-frame reordering cannot be locked until a later frame has been
+            // submitted
+            self.reader.wait_for_output_ready();
+
+            if let Some(event_obj) = &buffer.event_obj {
+                event_obj.wait()?;
+            }
 
             if buffer.end_of_stream {
                 return Err(NvEncError::EndOfStream);
@@ -24,21 +51,41 @@ impl EncoderOutput {
 
             let mut lock_params: crate::sys::NV_ENC_LOCK_BITSTREAM =
                 unsafe { MaybeUninit::zeroed().assume_init() };
-            lock_params.version = crate::sys::NV_ENC_LOCK_BITSTREAM_VER;
+            lock_params.version = self.reader.struct_versions().lock_bitstream;
             lock_params.outputBitstream = buffer.output_buffer.as_ptr();
+            // SAFETY: The reader has exclusive access to the item while reading it
+            let slice_offsets = unsafe { &mut *buffer.slice_offsets.get() };
+            if !slice_offsets.is_empty() {
+                lock_params.sliceOffsets = slice_offsets.as_mut_ptr();
+            }
+            // Blocks until the output is ready when there is no completion event to wait on
+            lock_params.set_doNotWait(0);
+            // Fills in the intra/inter block counts and the average motion vector
+            lock_params.set_getRCStats(1);
 
             unsafe {
                 self.reader.lock_bitstream(&mut lock_params)?;
             }
 
-            consume_output(&lock_params);
+            // SAFETY: Same as `slice_offsets`
+            let user_data = unsafe { (*buffer.user_data.get()).take() };
+            let packet = EncodedPacket::new(&lock_params, user_data);
+            self.session_stats
+                .borrow_mut()
+                .add_frame(&packet.stats, Instant::now());
+            consume_output(packet);
 
             unsafe {
                 self.reader.unlock_bitstream(lock_params.outputBitstream)?;
-                self.reader.unmap_input_resource(buffer.mapped_input)?;
             }
+            buffer.unmap_input(&self.reader)?;
 
             Ok(())
         })
     }
+
+    /// Same as `wait_for_output` but copies the output into `packet`, reusing its allocations.
+    pub fn wait_for_owned_packet(&self, packet: &mut OwnedEncodedPacket) -> Result<()> {
+        self.wait_for_output(|output| packet.copy_from(output))
+    }
 }
@@ -1,5 +1,7 @@
 use super::EventObjectTrait;
 
+/// Completion events are only supported on Windows. Only synchronous mode, which does not use
+/// events, is available elsewhere.
 #[repr(transparent)]
 pub struct EventObject(());
 
@@ -8,7 +10,7 @@ impl EventObjectTrait for EventObject {
         Ok(EventObject(()))
     }
 
-    fn wait(&self, _timeout_millis: u32) -> crate::Result<()> {
+    fn wait(&self) -> crate::Result<()> {
         Ok(())
     }
 
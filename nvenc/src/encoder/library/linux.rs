@@ -0,0 +1,169 @@
+use super::LibraryImplTrait;
+use crate::{NvEncError, Result};
+use std::{
+    ffi::{c_void, CString},
+    fs::{File, Metadata},
+    os::unix::{fs::MetadataExt, io::AsRawFd},
+    path::{Path, PathBuf},
+    ptr::NonNull,
+};
+
+/// Directories where the driver installs `libnvidia-encode.so.1`, in the order they are searched.
+/// Writing to these requires root so they serve the same role as C:\Windows\System32.
+const LIBRARY_SEARCH_DIRS: [&str; 8] = [
+    "/usr/lib/x86_64-linux-gnu",
+    "/usr/lib/aarch64-linux-gnu",
+    "/usr/lib64",
+    "/usr/lib",
+    "/lib/x86_64-linux-gnu",
+    "/lib64",
+    "/lib",
+    // Mount point used by the NVIDIA container runtime
+    "/usr/local/nvidia/lib64",
+];
+
+/// RAII wrapper for a handle returned by `dlopen`.
+#[repr(transparent)]
+pub struct LibraryImpl(NonNull<c_void>);
+
+// SAFETY: The handle returned by `dlopen` is not tied to the thread that opened it.
+unsafe impl Send for LibraryImpl {}
+
+impl Drop for LibraryImpl {
+    fn drop(&mut self) {
+        unsafe {
+            // Deliberately ignoring failure
+            let _ignored_result = libc::dlclose(self.0.as_ptr());
+        }
+    }
+}
+
+impl LibraryImplTrait for LibraryImpl {
+    const LIBRARY_NAME: &'static str = "libnvidia-encode.so.1";
+
+    const CUDA_LIBRARY_NAME: &'static str = "libcuda.so.1";
+
+    /// There is no code signing for shared objects on Linux so this instead checks that the
+    /// library, and every directory leading up to it, is owned by root and not writable by anyone
+    /// else. Bare filenames must also resolve to one of the driver directories.
+    ///
+    /// These checks go through the path, which could be swapped out before the library is opened,
+    /// so `load` checks the ownership of the file again once it is open.
+    fn is_library_signed(filename: &str) -> bool {
+        let path = match find_library(filename) {
+            Some(path) => path,
+            None => return false,
+        };
+        // Follow symlinks (`libnvidia-encode.so.1` -> `libnvidia-encode.so.<driver version>`) so
+        // the checks apply to the file that is actually going to be mapped
+        let canonical_path = match path.canonicalize() {
+            Ok(canonical_path) => canonical_path,
+            Err(_) => return false,
+        };
+
+        // An explicit path is the caller's choice of location so only the ownership is checked
+        if !Path::new(filename).is_absolute()
+            && !LIBRARY_SEARCH_DIRS
+                .iter()
+                .any(|dir| canonical_path.starts_with(dir))
+        {
+            return false;
+        }
+
+        match std::fs::metadata(&canonical_path) {
+            Ok(metadata) if metadata.is_file() => {}
+            _ => return false,
+        }
+        canonical_path.ancestors().all(|path| {
+            std::fs::metadata(path).is_ok_and(|metadata| is_root_owned_and_read_only(&metadata))
+        })
+    }
+
+    /// Open a .so from the driver directories, or from `lib_name` if it is an absolute path.
+    ///
+    /// The file is opened once and its ownership checked on the open descriptor, which is then
+    /// passed to `dlopen` through `/proc/self/fd`, so the file that is checked is the file that
+    /// is mapped. Returns `LibraryNotSigned` if it is not owned by root or is writable by others.
+    fn load(lib_name: &str) -> Result<Self> {
+        let path = find_library(lib_name).ok_or(NvEncError::LibraryLoadingFailed)?;
+        let file = File::open(path).map_err(|_| NvEncError::LibraryLoadingFailed)?;
+        match file.metadata() {
+            Ok(metadata) if metadata.is_file() && is_root_owned_and_read_only(&metadata) => {}
+            _ => return Err(NvEncError::LibraryNotSigned),
+        }
+        let fd_path = CString::new(format!("/proc/self/fd/{}", file.as_raw_fd()))
+            .map_err(|_| NvEncError::LibraryLoadingFailed)?;
+
+        // The descriptor only has to stay open until `dlopen` has mapped the file
+        let lib = unsafe { libc::dlopen(fd_path.as_ptr(), libc::RTLD_NOW | libc::RTLD_LOCAL) };
+        drop(file);
+        NonNull::new(lib)
+            .map(LibraryImpl)
+            .ok_or(NvEncError::LibraryLoadingFailed)
+    }
+
+    /// Extracts a function pointer from the library. The returned function pointer is bound to
+    /// the lifetime `&self`.
+    unsafe fn fn_ptr<T>(&self, fn_name: &str) -> Option<T> {
+        let fn_name = CString::new(fn_name).ok()?;
+        let ptr = libc::dlsym(self.0.as_ptr(), fn_name.as_ptr());
+        if ptr.is_null() {
+            None
+        } else {
+            // Fancy transmute
+            Some((&ptr as *const _ as *const T).read())
+        }
+    }
+}
+
+/// Resolves `filename` against `LIBRARY_SEARCH_DIRS` unless it is already an absolute path.
+/// `LD_LIBRARY_PATH` is intentionally ignored.
+fn find_library(filename: &str) -> Option<PathBuf> {
+    let path = Path::new(filename);
+    if path.is_absolute() {
+        return Some(path.to_path_buf());
+    }
+    LIBRARY_SEARCH_DIRS
+        .iter()
+        .map(|dir| Path::new(dir).join(path))
+        .find(|path| path.is_file())
+}
+
+/// True if the file is owned by root and is not writable by its group or by other users.
+fn is_root_owned_and_read_only(metadata: &Metadata) -> bool {
+    metadata.uid() == 0 && metadata.mode() & 0o022 == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[test]
+    fn library_loading() {
+        assert!(LibraryImpl::is_library_signed("libnvidia-encode.so.1"));
+        LibraryImpl::load("libnvidia-encode.so.1").unwrap();
+    }
+
+    #[test]
+    fn untrusted_library_rejected() {
+        // The temp directory is world-writable so anything inside it must be rejected
+        let path = std::env::temp_dir().join("libnvidia-encode-untrusted.so.1");
+        std::fs::write(&path, b"").unwrap();
+        assert!(!LibraryImpl::is_library_signed(path.to_str().unwrap()));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn writable_library_not_loaded() {
+        // Skips the path checks, the open file itself must be rejected
+        let path = std::env::temp_dir().join("libnvidia-encode-writable.so.1");
+        std::fs::write(&path, b"").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o666)).unwrap();
+        assert!(matches!(
+            LibraryImpl::load(path.to_str().unwrap()),
+            Err(NvEncError::LibraryNotSigned)
+        ));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,6 +1,10 @@
+#[cfg(target_os = "linux")]
+mod linux;
 #[cfg(windows)]
 mod windows;
 
+#[cfg(target_os = "linux")]
+use self::linux::LibraryImpl;
 #[cfg(windows)]
 use self::windows::LibraryImpl;
 
@@ -12,6 +16,9 @@ trait LibraryImplTrait: Sized {
     /// Filename of the .dll or .so
     const LIBRARY_NAME: &'static str;
 
+    /// Filename of the CUDA driver API .dll or .so
+    const CUDA_LIBRARY_NAME: &'static str;
+
     /// Checks if the library is signed.
     fn is_library_signed(filename: &str) -> bool;
 
@@ -26,10 +33,21 @@ pub struct Library(LibraryImpl);
 
 impl Library {
     pub fn load() -> Result<Self> {
-        if !LibraryImpl::is_library_signed(LibraryImpl::LIBRARY_NAME) {
+        Library::load_from(LibraryImpl::LIBRARY_NAME)
+    }
+
+    /// Load the CUDA driver API library that is installed alongside the NvEnc library.
+    pub fn load_cuda() -> Result<Self> {
+        Library::load_from(LibraryImpl::CUDA_LIBRARY_NAME)
+    }
+
+    /// Load the library from `lib_name`, which can either be the bare filename or, on Linux, an
+    /// absolute path to it.
+    pub fn load_from(lib_name: &str) -> Result<Self> {
+        if !LibraryImpl::is_library_signed(lib_name) {
             Err(NvEncError::LibraryNotSigned)
         } else {
-            LibraryImpl::load(LibraryImpl::LIBRARY_NAME).map(|lib| Library(lib))
+            LibraryImpl::load(lib_name).map(|lib| Library(lib))
         }
     }
 
@@ -37,6 +55,12 @@ impl Library {
         &self.0
     }
 
+    /// Extracts a function pointer from the library. The returned function pointer is bound to
+    /// the lifetime `&self`.
+    pub unsafe fn fn_ptr<T>(&self, fn_name: &str) -> Option<T> {
+        self.as_inner().fn_ptr(fn_name)
+    }
+
     pub fn get_max_supported_version(&self) -> Result<u32> {
         const FN_NAME: &'static str = "NvEncodeAPIGetMaxSupportedVersion";
         type GetMaxSupportedVersion = unsafe extern "C" fn(*mut u32) -> crate::sys::NVENCSTATUS;
@@ -56,7 +80,11 @@ impl Library {
         }
     }
 
-    pub fn get_function_list(&self) -> Result<crate::sys::NV_ENCODE_API_FUNCTION_LIST> {
+    /// `version` is the `NV_ENCODE_API_FUNCTION_LIST_VER` of the API version to use.
+    pub fn get_function_list(
+        &self,
+        version: u32,
+    ) -> Result<crate::sys::NV_ENCODE_API_FUNCTION_LIST> {
         const FN_NAME: &'static str = "NvEncodeAPICreateInstance";
         type CreateInstance = unsafe extern "C" fn(
             *mut crate::sys::NV_ENCODE_API_FUNCTION_LIST,
@@ -72,7 +100,7 @@ impl Library {
             let mut fn_list: crate::sys::NV_ENCODE_API_FUNCTION_LIST =
                 MaybeUninit::zeroed().assume_init();
             // The version needs to be set or the API will return an error
-            fn_list.version = crate::sys::NV_ENCODE_API_FUNCTION_LIST_VER;
+            fn_list.version = version;
 
             let status = create_instance(&mut fn_list);
 
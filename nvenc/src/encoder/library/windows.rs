@@ -40,6 +40,8 @@ impl Drop for LibraryImpl {
 impl LibraryImplTrait for LibraryImpl {
     const LIBRARY_NAME: &'static str = "nvEncodeAPI64.dll";
 
+    const CUDA_LIBRARY_NAME: &'static str = "nvcuda.dll";
+
     /// Checks if the library is signed. This is different from passing the
     /// `LOAD_LIBRARY_REQUIRE_SIGNED_TARGET` flag to `LoadLibraryExA`.
     // Translated into Rust from:
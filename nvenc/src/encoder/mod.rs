@@ -1,15 +1,31 @@
+mod api_version;
+#[cfg(feature = "async")]
+mod async_output;
 mod buffer_items;
 mod builder;
+mod capabilities;
 mod config;
 pub mod device;
+mod dpb;
 mod encoder_input;
 mod encoder_output;
 mod event;
 mod library;
+mod packet;
+mod qp_map;
 mod raw_encoder;
 mod shared;
+mod stats;
 mod texture;
 
+#[cfg(feature = "async")]
+pub use self::async_output::AsyncEncoderOutput;
 pub use self::{
-    builder::EncoderBuilder, encoder_input::EncoderInput, encoder_output::EncoderOutput,
+    builder::EncoderBuilder,
+    capabilities::EncoderCapabilities,
+    encoder_input::{EncodeFrameOptions, EncoderInput, HostPlane},
+    encoder_output::EncoderOutput,
+    packet::{EncodedPacket, OwnedEncodedPacket},
+    qp_map::{QpMap, RegionOfInterest},
+    stats::{FrameStats, SessionStats},
 };
@@ -0,0 +1,129 @@
+use super::stats::FrameStats;
+use crate::PictureType;
+
+/// The output of a single frame, borrowed from the locked output buffer.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct EncodedPacket<'a> {
+    /// The encoded bitstream.
+    pub data: &'a [u8],
+    pub picture_type: PictureType,
+    /// Timestamp the frame was submitted with.
+    pub timestamp: u64,
+    /// `EncodeFrameOptions::duration` of the frame.
+    pub duration: u64,
+    /// Index of the frame in encoding order.
+    pub frame_index: u32,
+    pub stats: FrameStats,
+    /// Index of the long-term reference that the frame was marked as, `None` if it was not.
+    pub ltr_index: Option<u32>,
+    /// Bitmap of the long-term references that the frame was predicted from.
+    pub ltr_bitmap: u32,
+    /// Byte offsets of the slices (H.264, HEVC) or tiles (AV1) in `data`. Empty unless
+    /// `EncoderBuilder::report_slice_offsets` was enabled.
+    pub slice_offsets: &'a [u32],
+    /// `EncodeFrameOptions::user_data` of the frame.
+    pub user_data: Option<Vec<u8>>,
+}
+
+impl<'a> EncodedPacket<'a> {
+    pub(crate) fn new(
+        output: &'a crate::sys::NV_ENC_LOCK_BITSTREAM,
+        user_data: Option<Vec<u8>>,
+    ) -> Self {
+        // SAFETY: The bitstream stays locked, and the slice offsets are not touched, until the
+        // packet has been consumed
+        let data = unsafe {
+            std::slice::from_raw_parts(
+                output.bitstreamBufferPtr as *const u8,
+                output.bitstreamSizeInBytes as usize,
+            )
+        };
+        let slice_offsets = if output.sliceOffsets.is_null() {
+            &[]
+        } else {
+            // SAFETY: Points to the offsets of the item that is being read, which has room for
+            // more slices than the encoder can produce
+            unsafe { std::slice::from_raw_parts(output.sliceOffsets, output.numSlices as usize) }
+        };
+
+        EncodedPacket {
+            data,
+            picture_type: PictureType::from_nvenc(&output.pictureType),
+            timestamp: output.outputTimeStamp,
+            duration: output.outputDuration,
+            frame_index: output.frameIdx,
+            stats: FrameStats::from_nvenc(output),
+            ltr_index: (output.ltrFrame() != 0).then_some(output.ltrFrameIdx),
+            ltr_bitmap: output.ltrFrameBitmap,
+            slice_offsets,
+            user_data,
+        }
+    }
+
+    pub fn is_idr(&self) -> bool {
+        self.picture_type == PictureType::Idr
+    }
+}
+
+/// An `EncodedPacket` that owns its data. Reusing it with `EncoderOutput::wait_for_owned_packet`
+/// avoids allocating for every frame.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OwnedEncodedPacket {
+    pub data: Vec<u8>,
+    pub picture_type: PictureType,
+    pub timestamp: u64,
+    pub duration: u64,
+    pub frame_index: u32,
+    pub stats: FrameStats,
+    pub ltr_index: Option<u32>,
+    pub ltr_bitmap: u32,
+    pub slice_offsets: Vec<u32>,
+    pub user_data: Option<Vec<u8>>,
+}
+
+impl Default for OwnedEncodedPacket {
+    fn default() -> Self {
+        OwnedEncodedPacket {
+            data: Vec::new(),
+            picture_type: PictureType::Unknown,
+            timestamp: 0,
+            duration: 0,
+            frame_index: 0,
+            stats: FrameStats::default(),
+            ltr_index: None,
+            ltr_bitmap: 0,
+            slice_offsets: Vec::new(),
+            user_data: None,
+        }
+    }
+}
+
+impl OwnedEncodedPacket {
+    /// Copies `packet` into this one, reusing the allocations of `data` and `slice_offsets`.
+    pub fn copy_from(&mut self, packet: EncodedPacket<'_>) {
+        self.data.clear();
+        self.data.extend_from_slice(packet.data);
+        self.picture_type = packet.picture_type;
+        self.timestamp = packet.timestamp;
+        self.duration = packet.duration;
+        self.frame_index = packet.frame_index;
+        self.stats = packet.stats;
+        self.ltr_index = packet.ltr_index;
+        self.ltr_bitmap = packet.ltr_bitmap;
+        self.slice_offsets.clear();
+        self.slice_offsets.extend_from_slice(packet.slice_offsets);
+        self.user_data = packet.user_data;
+    }
+
+    pub fn is_idr(&self) -> bool {
+        self.picture_type == PictureType::Idr
+    }
+}
+
+impl From<EncodedPacket<'_>> for OwnedEncodedPacket {
+    fn from(packet: EncodedPacket<'_>) -> Self {
+        let mut owned = OwnedEncodedPacket::default();
+        owned.copy_from(packet);
+        owned
+    }
+}
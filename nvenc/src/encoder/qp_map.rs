@@ -0,0 +1,141 @@
+use crate::{Codec, NvEncError, Result};
+
+/// Highest emphasis level of `QpMapMode::Emphasis`.
+const MAX_EMPHASIS_LEVEL: i8 = 5;
+
+/// Values of the blocks of a frame: QP offsets with `QpMapMode::Delta` or emphasis levels from 0
+/// to 5 with `QpMapMode::Emphasis`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum QpMap {
+    /// One value per block in raster order. Blocks are macroblocks (16x16) for H.264, CTBs
+    /// (32x32) for HEVC and superblocks (64x64) for AV1, see `EncoderInput::qp_map_dimensions`.
+    Blocks(Vec<i8>),
+    /// Rectangles that are rasterized to the blocks they overlap. Where regions overlap the last
+    /// one wins, and blocks outside of every region are 0.
+    Regions(Vec<RegionOfInterest>),
+}
+
+/// A rectangle of the frame in pixels.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct RegionOfInterest {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// QP offset or emphasis level of the blocks in the region. Negative offsets spend more bits.
+    pub value: i8,
+}
+
+/// Width and height in pixels of the blocks of a QP map.
+fn block_size(codec: Codec) -> u32 {
+    match codec {
+        Codec::H264 => 16,
+        // NvEnc only supports 32x32 CTBs
+        Codec::Hevc => 32,
+        _ => 64,
+    }
+}
+
+/// Width and height in blocks of the QP map of a frame.
+pub(crate) fn dimensions(codec: Codec, width: u32, height: u32) -> (u32, u32) {
+    let block_size = block_size(codec);
+    (width.div_ceil(block_size), height.div_ceil(block_size))
+}
+
+impl QpMap {
+    /// Checks that the map fits a frame of `dimensions` blocks and that emphasis levels are in
+    /// range.
+    pub(crate) fn validate(&self, dimensions: (u32, u32), emphasis: bool) -> Result<()> {
+        let is_valid_value = |value: i8| !emphasis || (0..=MAX_EMPHASIS_LEVEL).contains(&value);
+        let is_valid = match self {
+            QpMap::Blocks(blocks) => {
+                blocks.len() == (dimensions.0 * dimensions.1) as usize
+                    && blocks.iter().all(|&value| is_valid_value(value))
+            }
+            QpMap::Regions(regions) => regions.iter().all(|region| is_valid_value(region.value)),
+        };
+        if is_valid {
+            Ok(())
+        } else {
+            Err(NvEncError::InvalidQpMap)
+        }
+    }
+
+    /// Writes the value of every block into `map`, reusing its allocation.
+    pub(crate) fn write_to(self, map: &mut Vec<i8>, codec: Codec, dimensions: (u32, u32)) {
+        let regions = match self {
+            QpMap::Blocks(blocks) => {
+                *map = blocks;
+                return;
+            }
+            QpMap::Regions(regions) => regions,
+        };
+
+        let (width, height) = dimensions;
+        map.clear();
+        map.resize((width * height) as usize, 0);
+
+        let block_size = block_size(codec);
+        for region in regions {
+            let left = region.x / block_size;
+            let top = region.y / block_size;
+            if region.width == 0 || region.height == 0 || left >= width || top >= height {
+                continue;
+            }
+            let right = (region.x.saturating_add(region.width - 1) / block_size + 1).min(width);
+            let bottom = (region.y.saturating_add(region.height - 1) / block_size + 1).min(height);
+            for row in top..bottom {
+                let start = (row * width) as usize;
+                for value in &mut map[start + left as usize..start + right as usize] {
+                    *value = region.value;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rasterize_regions() {
+        // 40x40 pixels in 16x16 macroblocks
+        let dimensions = dimensions(Codec::H264, 40, 40);
+        assert_eq!(dimensions, (3, 3));
+
+        let qp_map = QpMap::Regions(vec![
+            RegionOfInterest {
+                x: 0,
+                y: 0,
+                width: 17,
+                height: 16,
+                value: -4,
+            },
+            // Goes past the frame and overlaps the first region
+            RegionOfInterest {
+                x: 16,
+                y: 8,
+                width: 100,
+                height: 16,
+                value: 2,
+            },
+        ]);
+        assert!(qp_map.validate(dimensions, false).is_ok());
+        let mut map = vec![7; 20];
+        qp_map.write_to(&mut map, Codec::H264, dimensions);
+        #[rustfmt::skip]
+        assert_eq!(map, [
+            -4, 2, 2,
+            0, 2, 2,
+            0, 0, 0,
+        ]);
+
+        assert!(QpMap::Blocks(vec![0; 8])
+            .validate(dimensions, false)
+            .is_err());
+        assert!(QpMap::Blocks(vec![6; 9])
+            .validate(dimensions, true)
+            .is_err());
+    }
+}
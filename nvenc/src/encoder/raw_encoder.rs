@@ -1,6 +1,10 @@
 #![allow(dead_code)]
 
-use super::{device::DeviceImplTrait, library::Library};
+use super::{
+    api_version::{ApiVersion, StructVersions},
+    device::DeviceImplTrait,
+    library::Library,
+};
 use crate::{NvEncError, Result};
 use std::{mem::MaybeUninit, os::raw::c_void, ptr::NonNull};
 
@@ -8,15 +12,16 @@ use std::{mem::MaybeUninit, os::raw::c_void, ptr::NonNull};
 fn open_encode_session<T: DeviceImplTrait>(
     functions: &crate::sys::NV_ENCODE_API_FUNCTION_LIST,
     device: &T,
+    api_version: ApiVersion,
 ) -> Result<NonNull<c_void>> {
     let mut raw_encoder: *mut c_void = std::ptr::null_mut();
     unsafe {
         let mut session_params: crate::sys::NV_ENC_OPEN_ENCODE_SESSION_EX_PARAMS =
             MaybeUninit::zeroed().assume_init();
-        session_params.version = crate::sys::NV_ENC_OPEN_ENCODE_SESSION_EX_PARAMS_VER;
+        session_params.version = api_version.struct_versions().open_encode_session_ex_params;
         session_params.deviceType = T::device_type();
         session_params.device = device.as_ptr();
-        session_params.apiVersion = crate::sys::NVENCAPI_VERSION;
+        session_params.apiVersion = api_version.nvencapi_version();
 
         let status = (functions.nvEncOpenEncodeSessionEx.unwrap_unchecked())(
             &mut session_params,
@@ -32,8 +37,12 @@ fn open_encode_session<T: DeviceImplTrait>(
 }
 
 /// Checks the function list for null pointers. They all need to be valid since they are going to
-/// be `unwrap_unchecked` later.
-fn is_function_list_valid(functions: &crate::sys::NV_ENCODE_API_FUNCTION_LIST) -> bool {
+/// be `unwrap_unchecked` later, except for the ones added after `api_version` which are checked
+/// when called.
+fn is_function_list_valid(
+    functions: &crate::sys::NV_ENCODE_API_FUNCTION_LIST,
+    api_version: ApiVersion,
+) -> bool {
     let helper = || -> Option<()> {
         functions.nvEncOpenEncodeSession?;
         functions.nvEncGetEncodeGUIDCount?;
@@ -71,19 +80,50 @@ fn is_function_list_valid(functions: &crate::sys::NV_ENCODE_API_FUNCTION_LIST) -
         functions.nvEncCreateMVBuffer?;
         functions.nvEncDestroyMVBuffer?;
         functions.nvEncRunMotionEstimationOnly?;
-        functions.nvEncGetLastErrorString?;
-        functions.nvEncSetIOCudaStreams?;
-        functions.nvEncGetEncodePresetConfigEx?;
-        functions.nvEncGetSequenceParamEx?;
+        if api_version >= ApiVersion::V9_1 {
+            functions.nvEncGetLastErrorString?;
+            functions.nvEncSetIOCudaStreams?;
+        }
+        if api_version >= ApiVersion::V10_0 {
+            functions.nvEncGetEncodePresetConfigEx?;
+            functions.nvEncGetSequenceParamEx?;
+        }
         Some(())
     };
     helper().is_some()
 }
 
+/// `NV_ENC_CODEC_PIC_PARAMS` grew by 16 bytes in 12.0 which moved every field that comes after it
+/// in `NV_ENC_PIC_PARAMS`. Copies the params into the layout that the older versions expect.
+#[cfg(feature = "v12_0")]
+fn pic_params_before_v12_0(
+    pic_params: &crate::sys::NV_ENC_PIC_PARAMS,
+) -> nvenc_sys::v11_1::NV_ENC_PIC_PARAMS {
+    use std::mem::{offset_of, size_of};
+
+    let codec_params_offset = offset_of!(crate::sys::NV_ENC_PIC_PARAMS, codecPicParams);
+    let old_tail_offset =
+        codec_params_offset + size_of::<nvenc_sys::v11_1::NV_ENC_CODEC_PIC_PARAMS>();
+    let new_tail_offset = codec_params_offset + size_of::<crate::sys::NV_ENC_CODEC_PIC_PARAMS>();
+    let tail_len = size_of::<nvenc_sys::v11_1::NV_ENC_PIC_PARAMS>() - old_tail_offset;
+
+    let mut old_layout = MaybeUninit::<nvenc_sys::v11_1::NV_ENC_PIC_PARAMS>::zeroed();
+    unsafe {
+        let src = (pic_params as *const crate::sys::NV_ENC_PIC_PARAMS).cast::<u8>();
+        let dst = old_layout.as_mut_ptr().cast::<u8>();
+        // The H.264 and HEVC params are the same size in both versions so only the unused tail of
+        // the union is dropped
+        std::ptr::copy_nonoverlapping(src, dst, old_tail_offset);
+        std::ptr::copy_nonoverlapping(src.add(new_tail_offset), dst.add(old_tail_offset), tail_len);
+        old_layout.assume_init()
+    }
+}
+
 pub struct RawEncoder {
     encoder_ptr: NonNull<c_void>,
     functions: crate::sys::NV_ENCODE_API_FUNCTION_LIST,
     library: Library,
+    api_version: ApiVersion,
 }
 
 // SAFETY: The struct members would not be invalidated by being moved to another thread.
@@ -102,18 +142,35 @@ impl Drop for RawEncoder {
 }
 
 impl RawEncoder {
-    pub fn new<T: DeviceImplTrait>(device: &T, library: Library) -> Result<Self> {
-        let functions = library.get_function_list()?;
-        if !is_function_list_valid(&functions) {
+    pub fn new<T: DeviceImplTrait>(
+        device: &T,
+        library: Library,
+        api_version: ApiVersion,
+    ) -> Result<Self> {
+        let functions = library.get_function_list(api_version.struct_versions().function_list)?;
+        if !is_function_list_valid(&functions, api_version) {
             return Err(NvEncError::MalformedFunctionList);
         }
 
         Ok(RawEncoder {
-            encoder_ptr: open_encode_session(&functions, device)?,
+            encoder_ptr: open_encode_session(&functions, device, api_version)?,
             functions,
             library,
+            api_version,
         })
     }
+
+    /// The API version that the session was opened with.
+    #[inline]
+    pub fn api_version(&self) -> ApiVersion {
+        self.api_version
+    }
+
+    /// Struct version constants that need to be used with this session.
+    #[inline]
+    pub fn struct_versions(&self) -> &'static StructVersions {
+        self.api_version.struct_versions()
+    }
     #[inline]
     pub unsafe fn get_encode_guid_count(&self, encode_guid_count: *mut u32) -> Result<()> {
         let status = (self.functions.nvEncGetEncodeGUIDCount.unwrap_unchecked())(
@@ -298,10 +355,11 @@ impl RawEncoder {
         tuning_info: crate::sys::NV_ENC_TUNING_INFO,
         preset_config: *mut crate::sys::NV_ENC_PRESET_CONFIG,
     ) -> Result<()> {
-        let status = (self
+        let get_encode_preset_config_ex = self
             .functions
             .nvEncGetEncodePresetConfigEx
-            .unwrap_unchecked())(
+            .ok_or(NvEncError::UnsupportedByApiVersion)?;
+        let status = get_encode_preset_config_ex(
             self.encoder_ptr.as_ptr(),
             encode_guid,
             preset_guid,
@@ -388,6 +446,16 @@ impl RawEncoder {
         &self,
         encode_pic_params: *mut crate::sys::NV_ENC_PIC_PARAMS,
     ) -> Result<()> {
+        #[cfg(feature = "v12_0")]
+        let mut old_layout;
+        #[cfg(feature = "v12_0")]
+        let encode_pic_params = if self.api_version < ApiVersion::V12_0 {
+            old_layout = pic_params_before_v12_0(&*encode_pic_params);
+            std::ptr::addr_of_mut!(old_layout).cast()
+        } else {
+            encode_pic_params
+        };
+
         let status = (self.functions.nvEncEncodePicture.unwrap_unchecked())(
             self.encoder_ptr.as_ptr(),
             encode_pic_params,
@@ -635,11 +703,11 @@ impl RawEncoder {
         input_stream: crate::sys::NV_ENC_CUSTREAM_PTR,
         output_stream: crate::sys::NV_ENC_CUSTREAM_PTR,
     ) -> Result<()> {
-        let status = (self.functions.nvEncSetIOCudaStreams.unwrap_unchecked())(
-            self.encoder_ptr.as_ptr(),
-            input_stream,
-            output_stream,
-        );
+        let set_io_cuda_streams = self
+            .functions
+            .nvEncSetIOCudaStreams
+            .ok_or(NvEncError::UnsupportedByApiVersion)?;
+        let status = set_io_cuda_streams(self.encoder_ptr.as_ptr(), input_stream, output_stream);
         match NvEncError::from_nvenc_status(status) {
             None => Ok(()),
             Some(err) => Err(err),
@@ -651,7 +719,11 @@ impl RawEncoder {
         enc_init_params: *mut crate::sys::NV_ENC_INITIALIZE_PARAMS,
         sequence_param_payload: *mut crate::sys::NV_ENC_SEQUENCE_PARAM_PAYLOAD,
     ) -> Result<()> {
-        let status = (self.functions.nvEncGetSequenceParamEx.unwrap_unchecked())(
+        let get_sequence_param_ex = self
+            .functions
+            .nvEncGetSequenceParamEx
+            .ok_or(NvEncError::UnsupportedByApiVersion)?;
+        let status = get_sequence_param_ex(
             self.encoder_ptr.as_ptr(),
             enc_init_params,
             sequence_param_payload,
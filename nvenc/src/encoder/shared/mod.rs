@@ -1,16 +1,24 @@
 mod sync;
 
-use super::{
-    buffer_items::EncoderBufferItems, builder::BUFFER_SIZE, raw_encoder::RawEncoder,
-    texture::TextureBufferImplTrait,
+use super::{buffer_items::EncoderBufferItems, raw_encoder::RawEncoder};
+use crate::{Result, WaitStrategy};
+use std::{
+    ops::Deref,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
 };
-use crate::Result;
-use std::{mem::MaybeUninit, ops::Deref, sync::Arc};
-use sync::{CyclicBuffer, CyclicBufferReader, CyclicBufferWriter};
+use sync::{CyclicBuffer, CyclicBufferReader, CyclicBufferWriter, Waiter};
 
 struct NvidiaEncoderShared {
     raw_encoder: RawEncoder,
-    buffer: CyclicBuffer<EncoderBufferItems, BUFFER_SIZE>,
+    buffer: CyclicBuffer<EncoderBufferItems>,
+    /// Number of written items whose output can be locked. Lags behind the writes while the
+    /// encoder is holding on to frames for reordering. Wraps around like the indices of `buffer`.
+    outputs_ready: AtomicUsize,
+    /// Notified when `outputs_ready` changes.
+    outputs_ready_changed: Waiter,
 }
 
 impl Drop for NvidiaEncoderShared {
@@ -21,33 +29,26 @@ impl Drop for NvidiaEncoderShared {
     }
 }
 
-pub fn encoder_channel<T>(
+/// Create the writer/reader pair with `buffer_size` slots, which must be a power of two.
+/// `create_items` is called with the index of each slot on the buffer.
+pub fn encoder_channel<F>(
     raw_encoder: RawEncoder,
-    texture_buffer: &T,
+    buffer_size: usize,
+    wait_strategy: WaitStrategy,
+    mut create_items: F,
 ) -> Result<(NvidiaEncoderWriter, NvidiaEncoderReader)>
 where
-    T: TextureBufferImplTrait,
+    F: FnMut(&RawEncoder, usize) -> Result<EncoderBufferItems>,
 {
-    let buffer = unsafe {
-        let mut buffer = MaybeUninit::<[EncoderBufferItems; BUFFER_SIZE]>::uninit();
-
-        // Pointer to the start of the array's buffer
-        let mut ptr = (&mut *buffer.as_mut_ptr()).as_mut_ptr();
-
-        for i in 0..BUFFER_SIZE {
-            ptr.write(EncoderBufferItems::new(
-                &raw_encoder,
-                texture_buffer.get_texture(i),
-                texture_buffer.get_pitch_or_subresource_index(i),
-            )?);
-            ptr = ptr.offset(1);
-        }
-        buffer.assume_init()
-    };
+    let buffer = (0..buffer_size)
+        .map(|i| create_items(&raw_encoder, i))
+        .collect::<Result<Vec<_>>>()?;
 
     let shared_encoder = Arc::new(NvidiaEncoderShared {
         raw_encoder,
-        buffer: CyclicBuffer::new(buffer).unwrap(), // TODO: remove unwrap
+        buffer: CyclicBuffer::new(buffer, wait_strategy).unwrap(), // TODO: remove unwrap
+        outputs_ready: AtomicUsize::new(0),
+        outputs_ready_changed: Waiter::new(wait_strategy),
     });
     let writer = NvidiaEncoderWriter(shared_encoder.clone());
     let reader = NvidiaEncoderReader(shared_encoder);
@@ -63,14 +64,33 @@ pub struct NvidiaEncoderWriter(Arc<NvidiaEncoderShared>);
 unsafe impl Send for NvidiaEncoderWriter {}
 
 impl NvidiaEncoderWriter {
-    /// Modify an item on the buffer. Blocks if the buffer is full.
+    /// Modify the next item on the buffer without handing it to the reader. Blocks if the buffer
+    /// is full. Preparing again before `commit` modifies the same item.
     #[inline]
-    pub fn write<F, R>(&self, write_op: F) -> R
+    pub fn prepare<F, R>(&self, write_op: F) -> R
     where
         F: FnOnce(usize, &mut EncoderBufferItems) -> R,
     {
         let writer = unsafe { CyclicBufferWriter::from_shared_buffer(&self.0.buffer) };
-        writer.write(write_op)
+        writer.prepare(write_op)
+    }
+
+    /// Hand the prepared item to the reader. Only done once its frame has been submitted, so the
+    /// reader never locks the output of a frame that the encoder did not accept.
+    #[inline]
+    pub fn commit(&self) {
+        let writer = unsafe { CyclicBufferWriter::from_shared_buffer(&self.0.buffer) };
+        writer.commit();
+    }
+
+    /// Allow the reader to lock the output of every item written so far.
+    #[inline]
+    pub fn set_outputs_ready(&self) {
+        let writer = unsafe { CyclicBufferWriter::from_shared_buffer(&self.0.buffer) };
+        self.0
+            .outputs_ready
+            .store(writer.items_written(), Ordering::Release);
+        self.0.outputs_ready_changed.notify();
     }
 }
 
@@ -91,6 +111,18 @@ impl NvidiaEncoderReader {
         let reader = unsafe { CyclicBufferReader::from_shared_buffer(&self.0.buffer) };
         reader.read(read_op)
     }
+
+    /// Blocks until the output of the item currently being read can be locked. Must be called
+    /// inside `read`.
+    #[inline]
+    pub fn wait_for_output_ready(&self) {
+        let reader = unsafe { CyclicBufferReader::from_shared_buffer(&self.0.buffer) };
+        let items_read = reader.items_read();
+        // `outputs_ready` is never behind the reader so the item is ready once they differ
+        self.0
+            .outputs_ready_changed
+            .wait_until(|| self.0.outputs_ready.load(Ordering::Acquire) != items_read);
+    }
 }
 
 // TODO: Limit what methods are available to `NvidiaEncoderWriter` instead of blanket enabling
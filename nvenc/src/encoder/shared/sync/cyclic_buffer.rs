@@ -1,4 +1,5 @@
-use super::cache_aligned::CacheAligned;
+use super::{cache_aligned::CacheAligned, waiter::Waiter};
+use crate::WaitStrategy;
 use std::{
     cell::UnsafeCell,
     sync::atomic::{AtomicUsize, Ordering},
@@ -13,83 +14,103 @@ use std::{
 /// something must be written before it can be read and the item cannot be read again until after
 /// the next write.
 #[repr(C)]
-pub struct CyclicBuffer<T, const N: usize> {
+pub struct CyclicBuffer<T> {
     /// Index of the writer
     head: AtomicUsize,
     /// Index of the reader
     tail: AtomicUsize,
-    /// Array that holds the items
-    buffer: [UnsafeCell<CacheAligned<T>>; N],
+    /// Slice that holds the items
+    buffer: Box<[UnsafeCell<CacheAligned<T>>]>,
+    /// Notified by the reader after reading an item
+    not_full: Waiter,
+    /// Notified by the writer after writing an item
+    not_empty: Waiter,
 }
 
-impl<T, const N: usize> CyclicBuffer<T, N> {
-    /// Creates a new `CyclicBuffer`. Returns `None` if the buffer size is not a power of two or is
-    /// zero.
-    pub fn new(buffer: [T; N]) -> Option<Self> {
-        if !is_power_of_two(N) {
+impl<T> CyclicBuffer<T> {
+    /// Creates a new `CyclicBuffer` whose reader and writer wait for each other following
+    /// `wait_strategy`. Returns `None` if the buffer size is not a power of two or is zero.
+    pub fn new(buffer: Vec<T>, wait_strategy: WaitStrategy) -> Option<Self> {
+        if buffer.is_empty() || !is_power_of_two(buffer.len()) {
             return None;
         }
 
-        let buffer = buffer.map(|x| UnsafeCell::new(CacheAligned::new(x)));
+        let buffer = buffer
+            .into_iter()
+            .map(|x| UnsafeCell::new(CacheAligned::new(x)))
+            .collect();
 
         Some(CyclicBuffer {
             head: AtomicUsize::new(0),
             tail: AtomicUsize::new(0),
             buffer,
+            not_full: Waiter::new(wait_strategy),
+            not_empty: Waiter::new(wait_strategy),
         })
     }
 
     /// Returns the internal buffer. `&mut self` guarantees exclusive access from a single thread.
-    pub fn get_mut(&mut self) -> &mut [UnsafeCell<CacheAligned<T>>; N] {
+    pub fn get_mut(&mut self) -> &mut [UnsafeCell<CacheAligned<T>>] {
         &mut self.buffer
     }
 }
 
 #[repr(transparent)]
-pub struct CyclicBufferWriter<T, const N: usize>(CyclicBuffer<T, N>);
+pub struct CyclicBufferWriter<T>(CyclicBuffer<T>);
 
-impl<T, const N: usize> CyclicBufferWriter<T, N> {
+impl<T> CyclicBufferWriter<T> {
     /// Reinterpret a `&CyclicBuffer` as a `CyclicBufferWriter`.
-    pub unsafe fn from_shared_buffer(shared_buffer: &CyclicBuffer<T, N>) -> &Self {
+    pub unsafe fn from_shared_buffer(shared_buffer: &CyclicBuffer<T>) -> &Self {
         std::mem::transmute(shared_buffer)
     }
 
-    /// Modify an item on the buffer. Blocks if the buffer is full.
+    /// Modify the next item on the buffer without handing it to the reader. Blocks if the buffer
+    /// is full. Until `commit` is called, the item can be prepared again, e.g. to discard it.
     #[inline]
-    pub fn write<F, R>(&self, write_op: F) -> R
+    pub fn prepare<F, R>(&self, write_op: F) -> R
     where
         F: FnOnce(usize, &mut T) -> R,
     {
-        // Needs to synchronize-with the `store` below since this might be moved to another thread
+        // Needs to synchronize-with the `store` in `commit` since this might be moved to another
+        // thread
         let head = self.0.head.load(Ordering::Acquire);
-        loop {
+        let len = self.0.buffer.len();
+        self.0.not_full.wait_until(|| {
             let tail = self.0.tail.load(Ordering::Acquire);
 
             // Proceed if not full; The indices can wrap around so `!=` must be used here
-            if (head - tail) != N {
-                break;
-            } else {
-                std::thread::yield_now();
-            }
-        }
+            (head - tail) != len
+        });
 
-        let index = head & (N - 1);
-        let result = unsafe {
+        let index = head & (len - 1);
+        unsafe {
             let cell = self.0.buffer.get_unchecked(index);
             write_op(index, &mut *cell.get())
-        };
+        }
+    }
 
+    /// Hand the item modified by `prepare` to the reader.
+    #[inline]
+    pub fn commit(&self) {
+        let head = self.0.head.load(Ordering::Relaxed);
         self.0.head.store(head.wrapping_add(1), Ordering::Release);
-        result
+        self.0.not_empty.notify();
+    }
+
+    /// Number of items written so far. Wraps around on overflow.
+    #[inline]
+    pub fn items_written(&self) -> usize {
+        // Only the writer modifies `head`
+        self.0.head.load(Ordering::Relaxed)
     }
 }
 
 #[repr(transparent)]
-pub struct CyclicBufferReader<T, const N: usize>(CyclicBuffer<T, N>);
+pub struct CyclicBufferReader<T>(CyclicBuffer<T>);
 
-impl<T, const N: usize> CyclicBufferReader<T, N> {
+impl<T> CyclicBufferReader<T> {
     /// Reinterpret a `&CyclicBuffer` as a `CyclicBufferWriter`.
-    pub unsafe fn from_shared_buffer(shared_buffer: &CyclicBuffer<T, N>) -> &Self {
+    pub unsafe fn from_shared_buffer(shared_buffer: &CyclicBuffer<T>) -> &Self {
         std::mem::transmute(shared_buffer)
     }
 
@@ -101,26 +122,30 @@ impl<T, const N: usize> CyclicBufferReader<T, N> {
     {
         // Needs to synchronize-with the `store` below since this might be moved to another thread
         let tail = self.0.tail.load(Ordering::Acquire);
-        loop {
+        self.0.not_empty.wait_until(|| {
             let head = self.0.head.load(Ordering::Acquire);
 
             // Proceed if not empty; `head` is not always >= `tail` because of wrap-around
-            if head != tail {
-                break;
-            } else {
-                std::thread::yield_now();
-            }
-        }
+            head != tail
+        });
 
-        let index = tail & (N - 1);
+        let index = tail & (self.0.buffer.len() - 1);
         let result = unsafe {
             let cell = self.0.buffer.get_unchecked(index);
             read_op(&*cell.get())
         };
 
         self.0.tail.store(tail.wrapping_add(1), Ordering::Release);
+        self.0.not_full.notify();
         result
     }
+
+    /// Number of items read so far. Wraps around on overflow.
+    #[inline]
+    pub fn items_read(&self) -> usize {
+        // Only the reader modifies `tail`
+        self.0.tail.load(Ordering::Relaxed)
+    }
 }
 
 /// Tests if `num` is a power of two.
@@ -131,6 +156,10 @@ const fn is_power_of_two(num: usize) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::{
+        sync::Arc,
+        time::{Duration, Instant},
+    };
 
     #[test]
     fn power_of_two() {
@@ -146,50 +175,164 @@ mod tests {
         }
     }
 
+    const WAIT_STRATEGIES: [WaitStrategy; 3] = [
+        WaitStrategy::Spin,
+        WaitStrategy::SpinThenBlock { spins: 100 },
+        WaitStrategy::Block,
+    ];
+
+    struct DummyBuffer<T>(Arc<CyclicBuffer<T>>);
+
+    unsafe impl<T> Send for DummyBuffer<T> {}
+
+    // Helper function to restrict the usage of a `CyclicBuffer` between two threads only
+    // (writer and the reader)
+    fn dummy_channel<T>(
+        buffer: Vec<T>,
+        wait_strategy: WaitStrategy,
+    ) -> (DummyBuffer<T>, DummyBuffer<T>) {
+        let shared_buffer = Arc::new(CyclicBuffer::new(buffer, wait_strategy).unwrap());
+        let writer = DummyBuffer(shared_buffer.clone());
+        let reader = DummyBuffer(shared_buffer);
+        (writer, reader)
+    }
+
     #[test]
     fn buffer_sanity_check() {
-        use std::sync::Arc;
+        for wait_strategy in WAIT_STRATEGIES {
+            std::thread::scope(|s| {
+                const ITERS: i32 = 1000;
 
-        struct DummyBuffer<T, const N: usize>(Arc<CyclicBuffer<T, N>>);
+                let array = vec![0; 8];
+                let (writer, reader) = dummy_channel(array, wait_strategy);
 
-        unsafe impl<T, const N: usize> Send for DummyBuffer<T, N> {}
+                s.spawn(move || {
+                    let writer = writer;
+                    let writer = unsafe { CyclicBufferWriter::from_shared_buffer(&writer.0) };
+                    for i in 0..ITERS {
+                        writer.prepare(|_i, val| {
+                            *val = i;
+                        });
+                        writer.commit();
+                    }
+                });
 
-        // Helper function to restrict the usage of a `CyclicBuffer` between two threads only
-        // (writer and the reader)
-        fn dummy_channel<T, const N: usize>(
-            buffer: [T; N],
-        ) -> (DummyBuffer<T, N>, DummyBuffer<T, N>) {
-            let shared_buffer = Arc::new(CyclicBuffer::new(buffer).unwrap());
-            let writer = DummyBuffer(shared_buffer.clone());
-            let reader = DummyBuffer(shared_buffer);
-            (writer, reader)
+                s.spawn(|| {
+                    let reader = reader;
+                    let reader = unsafe { CyclicBufferReader::from_shared_buffer(&reader.0) };
+                    for i in 0..ITERS {
+                        reader.read(|val| {
+                            assert_eq!(i, *val);
+                        });
+                    }
+                });
+            });
         }
+    }
 
-        std::thread::scope(|s| {
-            const ITERS: i32 = 1000;
+    #[test]
+    fn discarded_writes_are_not_read() {
+        for wait_strategy in WAIT_STRATEGIES {
+            std::thread::scope(|s| {
+                const ITERS: i32 = 1000;
+                fn committed(i: i32) -> bool {
+                    i % 3 != 0
+                }
 
-            let array = [0; 8];
-            let (writer, reader) = dummy_channel(array);
+                let array = vec![0; 4];
+                let (writer, reader) = dummy_channel(array, wait_strategy);
 
-            s.spawn(move || {
-                let writer = writer;
-                let writer = unsafe { CyclicBufferWriter::from_shared_buffer(&writer.0) };
-                for i in 0..ITERS {
-                    writer.write(|_i, val| {
-                        *val = i;
-                    });
-                }
+                s.spawn(move || {
+                    let writer = writer;
+                    let writer = unsafe { CyclicBufferWriter::from_shared_buffer(&writer.0) };
+                    for i in 0..ITERS {
+                        writer.prepare(|_i, val| {
+                            *val = i;
+                        });
+                        // Like a frame whose submission failed, the next write reuses the item
+                        if committed(i) {
+                            writer.commit();
+                        }
+                    }
+                });
+
+                s.spawn(|| {
+                    let reader = reader;
+                    let reader = unsafe { CyclicBufferReader::from_shared_buffer(&reader.0) };
+                    for i in (0..ITERS).filter(|&i| committed(i)) {
+                        reader.read(|val| {
+                            assert_eq!(i, *val);
+                        });
+                    }
+                });
             });
+        }
+    }
+
+    /// Compares the latency and CPU usage of the wait strategies, once with a writer that is
+    /// mostly idle like a 1000 FPS encoder and once with a writer that never waits. Run with
+    /// `cargo test --release wait_strategy_benchmark -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn wait_strategy_benchmark() {
+        const ITERS: u32 = 1000;
 
-            s.spawn(|| {
-                let reader = reader;
-                let reader = unsafe { CyclicBufferReader::from_shared_buffer(&reader.0) };
-                for i in 0..ITERS {
-                    reader.read(|val| {
-                        assert_eq!(i, *val);
+        for interval in [Some(Duration::from_millis(1)), None] {
+            for wait_strategy in WAIT_STRATEGIES {
+                let array = vec![Instant::now(); 8];
+                let (writer, reader) = dummy_channel(array, wait_strategy);
+                let start = Instant::now();
+
+                let (latency, cpu_time) = std::thread::scope(|s| {
+                    s.spawn(move || {
+                        let writer = writer;
+                        let writer = unsafe { CyclicBufferWriter::from_shared_buffer(&writer.0) };
+                        for _ in 0..ITERS {
+                            if let Some(interval) = interval {
+                                std::thread::sleep(interval);
+                            }
+                            writer.prepare(|_i, val| {
+                                *val = Instant::now();
+                            });
+                            writer.commit();
+                        }
                     });
-                }
-            });
-        });
+
+                    s.spawn(move || {
+                        let reader = reader;
+                        let reader = unsafe { CyclicBufferReader::from_shared_buffer(&reader.0) };
+                        let cpu_start = thread_cpu_time();
+                        let mut latency = Duration::ZERO;
+                        for _ in 0..ITERS {
+                            reader.read(|written| latency += written.elapsed());
+                        }
+                        (latency / ITERS, thread_cpu_time() - cpu_start)
+                    })
+                    .join()
+                    .unwrap()
+                });
+
+                println!(
+                    "{wait_strategy:?}, writer interval {interval:?}: {:?} total, \
+                     {latency:?} average latency, {cpu_time:?} reader CPU time",
+                    start.elapsed(),
+                );
+            }
+        }
+    }
+
+    /// CPU time used by the current thread, or zero where that is not implemented.
+    fn thread_cpu_time() -> Duration {
+        #[cfg(target_os = "linux")]
+        {
+            let mut time = libc::timespec {
+                tv_sec: 0,
+                tv_nsec: 0,
+            };
+            unsafe { libc::clock_gettime(libc::CLOCK_THREAD_CPUTIME_ID, &mut time) };
+            Duration::new(time.tv_sec as u64, time.tv_nsec as u32)
+        }
+        #[cfg(not(target_os = "linux"))]
+        Duration::ZERO
     }
 }
@@ -1,4 +1,6 @@
 mod cache_aligned;
 mod cyclic_buffer;
+mod waiter;
 
 pub(super) use cyclic_buffer::{CyclicBuffer, CyclicBufferReader, CyclicBufferWriter};
+pub(super) use waiter::Waiter;
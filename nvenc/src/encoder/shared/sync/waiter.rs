@@ -0,0 +1,83 @@
+use crate::WaitStrategy;
+use std::sync::{
+    atomic::{fence, AtomicUsize, Ordering},
+    Condvar, Mutex, PoisonError,
+};
+
+/// Lets a thread wait for a condition that another thread makes true, following a
+/// `WaitStrategy`.
+pub struct Waiter {
+    strategy: WaitStrategy,
+    mutex: Mutex<()>,
+    condvar: Condvar,
+    /// Number of threads that are blocked or about to block.
+    blocked: AtomicUsize,
+}
+
+impl Waiter {
+    pub fn new(strategy: WaitStrategy) -> Self {
+        Waiter {
+            strategy,
+            mutex: Mutex::new(()),
+            condvar: Condvar::new(),
+            blocked: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns once `condition` is true. The thread that makes it true has to call `notify`
+    /// afterwards.
+    #[inline]
+    pub fn wait_until<F>(&self, mut condition: F)
+    where
+        F: FnMut() -> bool,
+    {
+        let mut spins = 0;
+        while !condition() {
+            match self.strategy {
+                WaitStrategy::Spin => {}
+                WaitStrategy::SpinThenBlock { spins: max_spins } if spins < max_spins => {
+                    spins += 1;
+                }
+                _ => return self.block_until(condition),
+            }
+            std::thread::yield_now();
+        }
+    }
+
+    #[cold]
+    fn block_until<F>(&self, mut condition: F)
+    where
+        F: FnMut() -> bool,
+    {
+        // Nothing panics while holding the lock
+        let mut guard = self.mutex.lock().unwrap_or_else(PoisonError::into_inner);
+        self.blocked.fetch_add(1, Ordering::Relaxed);
+        // Pairs with the fence in `notify`: either the condition is seen to be true here or
+        // `notify` sees that this thread is about to block
+        fence(Ordering::SeqCst);
+        while !condition() {
+            guard = self
+                .condvar
+                .wait(guard)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        self.blocked.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Wakes up the thread blocked in `wait_until`, if any. Must be called after making the
+    /// condition true.
+    #[inline]
+    pub fn notify(&self) {
+        if self.strategy == WaitStrategy::Spin {
+            return;
+        }
+
+        fence(Ordering::SeqCst);
+        if self.blocked.load(Ordering::Relaxed) != 0 {
+            // The blocking thread holds the lock until it waits on the condvar, so the
+            // notification cannot arrive between checking the condition and waiting
+            drop(self.mutex.lock().unwrap_or_else(PoisonError::into_inner));
+            self.condvar.notify_one();
+        }
+    }
+}
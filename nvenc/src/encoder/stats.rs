@@ -0,0 +1,163 @@
+use crate::PictureType;
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// Length of the window that `SessionStats::bitrate` is measured over.
+const BITRATE_WINDOW: Duration = Duration::from_secs(1);
+
+/// Statistics of a single encoded frame.
+///
+/// They are read from `NvEncLockBitstream` rather than `NvEncGetEncodeStats`, which is deprecated
+/// and not supported for CUDA devices.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct FrameStats {
+    /// Size of the encoded frame in bytes.
+    pub size: u32,
+    pub picture_type: PictureType,
+    pub average_qp: u32,
+    /// Sum of absolute transformed differences of the whole frame, i.e. the cost of motion
+    /// estimation and intra prediction. Lower means the frame was easier to predict.
+    pub satd: u32,
+    /// Number of intra-coded macroblocks (H.264), CTBs (HEVC) or superblocks (AV1).
+    pub intra_macroblocks: u32,
+    /// Number of inter-coded macroblocks (H.264), CTBs (HEVC) or superblocks (AV1), including
+    /// skipped ones.
+    pub inter_macroblocks: u32,
+    /// Average motion vector of the frame as `(x, y)`.
+    pub average_motion_vector: (i32, i32),
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        FrameStats {
+            size: 0,
+            picture_type: PictureType::Unknown,
+            average_qp: 0,
+            satd: 0,
+            intra_macroblocks: 0,
+            inter_macroblocks: 0,
+            average_motion_vector: (0, 0),
+        }
+    }
+}
+
+impl FrameStats {
+    pub(crate) fn from_nvenc(output: &crate::sys::NV_ENC_LOCK_BITSTREAM) -> Self {
+        FrameStats {
+            size: output.bitstreamSizeInBytes,
+            picture_type: PictureType::from_nvenc(&output.pictureType),
+            average_qp: output.frameAvgQP,
+            satd: output.frameSatd,
+            intra_macroblocks: output.intraMBCount,
+            inter_macroblocks: output.interMBCount,
+            average_motion_vector: (output.averageMVX, output.averageMVY),
+        }
+    }
+}
+
+/// Statistics of every frame that has been read from an `EncoderOutput`.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct SessionStats {
+    pub frames: u64,
+    pub bytes: u64,
+    /// Bits per second of the frames that were read in the last second, measured with the wall
+    /// clock.
+    pub bitrate: u64,
+    pub min_qp: u32,
+    pub average_qp: f64,
+    pub max_qp: u32,
+    pub idr_frames: u64,
+}
+
+/// Aggregates `FrameStats` into `SessionStats`.
+#[derive(Default)]
+pub(crate) struct SessionStatsCollector {
+    stats: SessionStats,
+    qp_sum: u64,
+    first_frame_time: Option<Instant>,
+    /// Time and size of the frames in the bitrate window, oldest first.
+    window: VecDeque<(Instant, u32)>,
+    window_bytes: u64,
+}
+
+impl SessionStatsCollector {
+    pub fn stats(&self) -> SessionStats {
+        self.stats
+    }
+
+    pub fn add_frame(&mut self, frame: &FrameStats, time: Instant) {
+        let stats = &mut self.stats;
+        if stats.frames == 0 {
+            stats.min_qp = frame.average_qp;
+        }
+        stats.frames += 1;
+        stats.bytes += u64::from(frame.size);
+        stats.min_qp = stats.min_qp.min(frame.average_qp);
+        stats.max_qp = stats.max_qp.max(frame.average_qp);
+        self.qp_sum += u64::from(frame.average_qp);
+        stats.average_qp = self.qp_sum as f64 / stats.frames as f64;
+        if frame.picture_type == PictureType::Idr {
+            stats.idr_frames += 1;
+        }
+
+        self.window.push_back((time, frame.size));
+        self.window_bytes += u64::from(frame.size);
+        while let Some(&(frame_time, size)) = self.window.front() {
+            if time.duration_since(frame_time) < BITRATE_WINDOW {
+                break;
+            }
+            self.window.pop_front();
+            self.window_bytes -= u64::from(size);
+        }
+
+        // Until the session is as long as the window, the window only covers the session
+        let first_frame_time = *self.first_frame_time.get_or_insert(time);
+        let window = time.duration_since(first_frame_time).min(BITRATE_WINDOW);
+        stats.bitrate = if window.is_zero() {
+            0
+        } else {
+            (self.window_bytes as f64 * 8.0 / window.as_secs_f64()) as u64
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_stats() {
+        let frame = |picture_type, size, average_qp| FrameStats {
+            size,
+            picture_type,
+            average_qp,
+            ..FrameStats::default()
+        };
+        let start = Instant::now();
+        let mut collector = SessionStatsCollector::default();
+
+        collector.add_frame(&frame(PictureType::Idr, 5000, 20), start);
+        assert_eq!(collector.stats().bitrate, 0);
+        collector.add_frame(
+            &frame(PictureType::P, 1000, 30),
+            start + Duration::from_millis(500),
+        );
+        // 6000 bytes in half a second
+        assert_eq!(collector.stats().bitrate, 96_000);
+        // The IDR frame has left the window
+        collector.add_frame(
+            &frame(PictureType::P, 2000, 25),
+            start + Duration::from_millis(1000),
+        );
+        assert_eq!(collector.stats().bitrate, 24_000);
+
+        let stats = collector.stats();
+        assert_eq!(stats.frames, 3);
+        assert_eq!(stats.bytes, 8000);
+        assert_eq!((stats.min_qp, stats.max_qp), (20, 30));
+        assert_eq!(stats.average_qp, 25.0);
+        assert_eq!(stats.idr_frames, 1);
+    }
+}
@@ -0,0 +1,55 @@
+use super::{IntoNvEncBufferFormat, TextureBufferImplTrait, TextureImplTrait};
+use crate::{
+    encoder::device::{CudaTexture, CudaTextureBuffer},
+    BufferFormat, Result,
+};
+use std::{ffi::c_void, mem::MaybeUninit};
+
+impl IntoNvEncBufferFormat for BufferFormat {
+    fn into_nvenc_buffer_format(&self) -> crate::sys::NV_ENC_BUFFER_FORMAT {
+        self.into_nvenc()
+    }
+}
+
+impl TextureImplTrait for CudaTexture {
+    fn resource_type() -> crate::sys::NV_ENC_INPUT_RESOURCE_TYPE {
+        crate::sys::NV_ENC_INPUT_RESOURCE_TYPE::NV_ENC_INPUT_RESOURCE_TYPE_CUDADEVICEPTR
+    }
+
+    fn as_ptr(&self) -> *mut c_void {
+        self.device_ptr as *mut c_void
+    }
+
+    fn build_register_resource_args(
+        &self,
+        pitch_or_subresource_index: u32,
+    ) -> Result<crate::sys::NV_ENC_REGISTER_RESOURCE> {
+        let mut register_resource_args: crate::sys::NV_ENC_REGISTER_RESOURCE =
+            unsafe { MaybeUninit::zeroed().assume_init() };
+        register_resource_args.resourceType = Self::resource_type();
+        register_resource_args.width = self.width;
+        register_resource_args.height = self.height;
+        register_resource_args.pitch = pitch_or_subresource_index;
+        register_resource_args.resourceToRegister = self.as_ptr();
+        register_resource_args.bufferFormat = self.format.into_nvenc_buffer_format();
+        register_resource_args.bufferUsage = crate::sys::NV_ENC_BUFFER_USAGE::NV_ENC_INPUT_IMAGE;
+        Ok(register_resource_args)
+    }
+}
+
+impl TextureBufferImplTrait for CudaTextureBuffer {
+    type Texture = CudaTexture;
+    type TextureFormat = BufferFormat;
+
+    fn texture_format(&self) -> Self::TextureFormat {
+        self.textures[0].format
+    }
+
+    fn get_texture(&self, index: usize) -> &Self::Texture {
+        &self.textures[index]
+    }
+
+    fn get_pitch_or_subresource_index(&self, index: usize) -> u32 {
+        self.textures[index].pitch as u32
+    }
+}
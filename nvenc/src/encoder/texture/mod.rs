@@ -1,10 +1,11 @@
+mod cuda;
 #[cfg(windows)]
 mod windows;
 
 #[cfg(windows)]
 pub use ::windows::Win32::Graphics::Direct3D11::ID3D11Texture2D as Texture;
 
-use crate::Result;
+use crate::{BufferFormat, Result};
 use std::ffi::c_void;
 
 pub trait IntoNvEncBufferFormat {
@@ -16,6 +17,8 @@ pub trait TextureImplTrait {
 
     fn as_ptr(&self) -> *mut c_void;
 
+    /// The `version` field is left for the caller to fill in since it depends on the API version
+    /// of the session.
     fn build_register_resource_args(
         &self,
         pitch_or_subresource_index: u32,
@@ -32,3 +35,128 @@ pub trait TextureBufferImplTrait {
 
     fn get_pitch_or_subresource_index(&self, index: usize) -> u32;
 }
+
+/// Location of a single plane inside a frame.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub struct Plane {
+    /// Offset in bytes from the start of the frame.
+    pub offset: usize,
+    pub pitch: usize,
+    /// Bytes in each row that hold pixel data.
+    pub width_in_bytes: usize,
+    pub height: usize,
+}
+
+/// Splits a frame whose planes are stored one after the other into its planes. Follows the same
+/// layout the Video Codec SDK samples use for CUDA and host memory frames:
+///
+/// https://github.com/NVIDIA/video-sdk-samples/blob/aa3544dcea2fe63122e4feb83bf805ea40e58dbe/Samples/NvCodec/NvEncoder/NvEncoder.cpp
+pub fn plane_layout(
+    format: BufferFormat,
+    width: u32,
+    height: u32,
+    pitch: usize,
+) -> impl Iterator<Item = Plane> {
+    let (width, height) = (width as usize, height as usize);
+    let chroma_420_height = height.div_ceil(2);
+
+    let (
+        luma_width_in_bytes,
+        chroma_width_in_bytes,
+        chroma_height,
+        chroma_pitch,
+        num_chroma_planes,
+    ) = match format {
+        BufferFormat::Nv12 => (width, width, chroma_420_height, pitch, 1),
+        BufferFormat::Yuv420_10Bit => (2 * width, 2 * width, chroma_420_height, pitch, 1),
+        BufferFormat::Yv12 | BufferFormat::Iyuv => (
+            width,
+            width.div_ceil(2),
+            chroma_420_height,
+            pitch.div_ceil(2),
+            2,
+        ),
+        BufferFormat::Yuv444 => (width, width, height, pitch, 2),
+        BufferFormat::Yuv444_10Bit => (2 * width, 2 * width, height, pitch, 2),
+        BufferFormat::Argb
+        | BufferFormat::Argb10
+        | BufferFormat::Ayuv
+        | BufferFormat::Abgr
+        | BufferFormat::Abgr10 => (4 * width, 0, 0, 0, 0),
+    };
+
+    let mut planes = [Plane::default(); 3];
+    planes[0] = Plane {
+        offset: 0,
+        pitch,
+        width_in_bytes: luma_width_in_bytes,
+        height,
+    };
+    let mut offset = pitch * height;
+    for plane in &mut planes[1..=num_chroma_planes] {
+        *plane = Plane {
+            offset,
+            pitch: chroma_pitch,
+            width_in_bytes: chroma_width_in_bytes,
+            height: chroma_height,
+        };
+        offset += chroma_pitch * chroma_height;
+    }
+    planes.into_iter().take(1 + num_chroma_planes)
+}
+
+/// Number of rows of the luma pitch needed to hold every plane of a frame. The pitch is assumed to
+/// be even so that the half-pitch chroma planes of YV12 and IYUV fill whole rows.
+pub fn frame_height_in_rows(format: BufferFormat, height: u32) -> usize {
+    let height = height as usize;
+    let chroma_rows = match format {
+        BufferFormat::Nv12
+        | BufferFormat::Yuv420_10Bit
+        | BufferFormat::Yv12
+        | BufferFormat::Iyuv => height.div_ceil(2),
+        BufferFormat::Yuv444 | BufferFormat::Yuv444_10Bit => 2 * height,
+        BufferFormat::Argb
+        | BufferFormat::Argb10
+        | BufferFormat::Ayuv
+        | BufferFormat::Abgr
+        | BufferFormat::Abgr10 => 0,
+    };
+    height + chroma_rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn planes_fill_frame_height_in_rows() {
+        let formats = [
+            BufferFormat::Nv12,
+            BufferFormat::Yv12,
+            BufferFormat::Iyuv,
+            BufferFormat::Yuv444,
+            BufferFormat::Yuv420_10Bit,
+            BufferFormat::Yuv444_10Bit,
+            BufferFormat::Argb,
+        ];
+        let pitch = 2048;
+        for format in formats {
+            let end = plane_layout(format, 1920, 1080, pitch)
+                .map(|plane| plane.offset + plane.pitch * plane.height)
+                .max()
+                .unwrap();
+            assert_eq!(end, pitch * frame_height_in_rows(format, 1080));
+        }
+    }
+
+    #[test]
+    fn yv12_chroma_planes() {
+        let planes: Vec<_> = plane_layout(BufferFormat::Yv12, 1920, 1080, 2048).collect();
+        assert_eq!(planes.len(), 3);
+        assert_eq!(planes[1].offset, 2048 * 1080);
+        assert_eq!(planes[1].pitch, 1024);
+        assert_eq!(planes[1].width_in_bytes, 960);
+        assert_eq!(planes[1].height, 540);
+        assert_eq!(planes[2].offset, 2048 * 1080 + 1024 * 540);
+    }
+}
@@ -53,22 +53,15 @@ impl TextureImplTrait for ID3D11Texture2D {
         };
         let subresource_index = pitch_or_subresource_index;
 
-        let register_resource_args = crate::sys::NV_ENC_REGISTER_RESOURCE {
-            version: crate::sys::NV_ENC_REGISTER_RESOURCE_VER,
-            resourceType: Self::resource_type(),
-            width,
-            height,
-            pitch: 0,
-            subResourceIndex: subresource_index,
-            resourceToRegister: self.as_ptr(),
-            registeredResource: std::ptr::null_mut(),
-            bufferFormat: format.into_nvenc_buffer_format(),
-            bufferUsage: crate::sys::NV_ENC_BUFFER_USAGE::NV_ENC_INPUT_IMAGE,
-            pInputFencePoint: std::ptr::null_mut(),
-            pOutputFencePoint: std::ptr::null_mut(),
-            reserved1: [0; 247],
-            reserved2: [std::ptr::null_mut(); 60],
-        };
+        let mut register_resource_args: crate::sys::NV_ENC_REGISTER_RESOURCE =
+            unsafe { MaybeUninit::zeroed().assume_init() };
+        register_resource_args.resourceType = Self::resource_type();
+        register_resource_args.width = width;
+        register_resource_args.height = height;
+        register_resource_args.subResourceIndex = subresource_index;
+        register_resource_args.resourceToRegister = self.as_ptr();
+        register_resource_args.bufferFormat = format.into_nvenc_buffer_format();
+        register_resource_args.bufferUsage = crate::sys::NV_ENC_BUFFER_USAGE::NV_ENC_INPUT_IMAGE;
         Ok(register_resource_args)
     }
 }
@@ -4,18 +4,24 @@ pub enum NvEncError {
     Sys(NonZeroNvencStatus),
 
     // TODO: Maybe split these into separate enums
-    #[error("The shared library for `nvEncodeAPI64` is not signed and may have been tampered.")]
+    #[error("The NvEnc shared library is not signed or is writable by non-root users and may have been tampered.")]
     LibraryNotSigned,
-    #[error("Loading the shared library for `nvEncodeAPI64` failed.")]
+    #[error("Loading the NvEnc shared library failed.")]
     LibraryLoadingFailed,
     #[error("Unable to locate `NvEncodeAPIGetMaxSupportedVersion` in the shared library.")]
     GetMaxSupportedVersionLoadingFailed,
     #[error("Unable to locate `NvEncodeAPICreateInstance` in the shared library.")]
     CreateInstanceLoadingFailed,
-    #[error("The installed driver does not support the version of the NvEnc API that this library is compiled with.")]
+    #[error("The installed driver does not support any of the NvEnc API versions that this library is compiled with.")]
     UnsupportedVersion,
     #[error("`NvEncodeAPICreateInstance` returned a malformed function list.")]
     MalformedFunctionList,
+    #[error(
+        "The feature is not available in the NvEnc API version supported by the installed driver."
+    )]
+    UnsupportedByApiVersion,
+    #[error("The NvEnc API returned a value that is not known to this library.")]
+    UnknownValue,
 
     #[error("The encoder for the current device does not support the codec")]
     UnsupportedCodec,
@@ -25,14 +31,40 @@ pub enum NvEncError {
     CodecProfileNotSupported,
     #[error("Encode preset is needed to build the encoder")]
     EncodePresetNotSet,
+    #[error("The encoder does not support the setting for the current codec or it conflicts with another setting")]
+    UnsupportedSetting,
+
+    #[error("Unable to load the CUDA driver API from the shared library.")]
+    CudaLoadingFailed,
+    #[error("A call to the CUDA driver API failed")]
+    CudaCallFailed,
 
     #[error("Failed creating a texture buffer")]
     TextureBufferCreationFailed,
-
+    #[error("The texture has a different format or is smaller than the encoder's input")]
+    InvalidTexture,
+
+    #[error("The frame does not match the input mode the encoder was built with")]
+    InputModeMismatch,
+    #[error("The host frame planes do not match the encoder's buffer format and size")]
+    InvalidHostFrame,
+    #[error("The long-term reference index is outside the number of LTR frames the encoder was built with")]
+    InvalidLtrIndex,
+    #[error("The QP map does not match the blocks of a frame or has invalid emphasis levels")]
+    InvalidQpMap,
+    #[error("The timestamp does not belong to a frame in the encoder's DPB")]
+    ReferenceFrameNotInDpb,
+    #[error("No valid reference frames are left in the DPB, an IDR frame is needed to recover")]
+    NoValidReferenceFrames,
+
+    #[error("Asynchronous encoding is only supported on Windows")]
+    AsyncEncodeNotSupported,
     #[error("Could not create a Windows event object")]
     EventObjectCreationFailed,
     #[error("Error while waiting for the event object to be signaled")]
     EventObjectWaitError,
+    #[error("Could not spawn the thread that reads the output")]
+    ThreadSpawnFailed,
 
     #[error("Input has signaled end of stream")]
     EndOfStream,
@@ -6,8 +6,20 @@ mod util;
 
 pub type Result<T> = std::result::Result<T, NvEncError>;
 
+#[cfg(feature = "async")]
+pub use self::encoder::AsyncEncoderOutput;
 pub use self::{
-    encoder::{device::*, EncoderBuilder, EncoderInput, EncoderOutput},
+    encoder::{
+        device::*, EncodeFrameOptions, EncodedPacket, EncoderBuilder, EncoderCapabilities,
+        EncoderInput, EncoderOutput, FrameStats, HostPlane, OwnedEncodedPacket, QpMap,
+        RegionOfInterest, SessionStats,
+    },
     error::NvEncError,
-    settings::{Codec, CodecProfile, EncodePreset, MultiPassSetting, TuningInfo},
+    settings::{
+        BFrameRefMode, BufferFormat, ChromaSampleLocation, Codec, CodecProfile, ColorDescription,
+        ColorPrimaries, ContentLightLevel, EncodePreset, ForcedPictureType, Guid, Level,
+        MasteringDisplay, MatrixCoefficients, MultiPassSetting, PictureStructure, PictureType, Qp,
+        QpMapMode, RateControl, SeiPayload, SliceMode, TimeCode, TransferCharacteristics,
+        TuningInfo, WaitStrategy,
+    },
 };
@@ -0,0 +1,232 @@
+//! Colour signalling written into the VUI and the HDR10 SEI messages. The discriminants are the
+//! code points from ITU-T H.273, which is what the VUI fields hold.
+
+/// Colour primaries, transfer characteristics and matrix coefficients of the encoded video.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ColorDescription {
+    pub primaries: ColorPrimaries,
+    pub transfer_characteristics: TransferCharacteristics,
+    pub matrix_coefficients: MatrixCoefficients,
+    /// Whether the samples use the full range (0-255 for 8-bit) instead of the limited range
+    /// (16-235 for luma in 8-bit).
+    pub full_range: bool,
+    /// Location of the chroma samples relative to the luma samples. `None` leaves it unsignaled,
+    /// which decoders treat as `ChromaSampleLocation::Left`.
+    pub chroma_sample_location: Option<ChromaSampleLocation>,
+}
+
+impl ColorDescription {
+    /// SDR video in limited range.
+    pub const BT709: ColorDescription = ColorDescription {
+        primaries: ColorPrimaries::Bt709,
+        transfer_characteristics: TransferCharacteristics::Bt709,
+        matrix_coefficients: MatrixCoefficients::Bt709,
+        full_range: false,
+        chroma_sample_location: None,
+    };
+
+    /// HDR10, i.e. BT.2020 with the PQ transfer function in limited range.
+    pub const HDR10: ColorDescription = ColorDescription {
+        primaries: ColorPrimaries::Bt2020,
+        transfer_characteristics: TransferCharacteristics::Pq,
+        matrix_coefficients: MatrixCoefficients::Bt2020NonConstant,
+        full_range: false,
+        chroma_sample_location: Some(ChromaSampleLocation::TopLeft),
+    };
+
+    /// BT.2020 with the HLG transfer function in limited range.
+    pub const HLG: ColorDescription = ColorDescription {
+        primaries: ColorPrimaries::Bt2020,
+        transfer_characteristics: TransferCharacteristics::Hlg,
+        matrix_coefficients: MatrixCoefficients::Bt2020NonConstant,
+        full_range: false,
+        chroma_sample_location: Some(ChromaSampleLocation::TopLeft),
+    };
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+#[repr(u32)]
+pub enum ColorPrimaries {
+    Bt709 = 1,
+    Bt2020 = 9,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+#[repr(u32)]
+pub enum TransferCharacteristics {
+    Bt709 = 1,
+    /// The sRGB/sYCC transfer function.
+    Srgb = 13,
+    /// Same curve as BT.709 but with more precision for 10-bit video.
+    Bt2020_10Bit = 14,
+    /// Perceptual quantizer, SMPTE ST 2084.
+    Pq = 16,
+    /// Hybrid log-gamma, ARIB STD-B67.
+    Hlg = 18,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+#[repr(u32)]
+pub enum MatrixCoefficients {
+    /// The samples are RGB/GBR instead of YUV.
+    Identity = 0,
+    Bt709 = 1,
+    Bt2020NonConstant = 9,
+}
+
+/// Location of the chroma samples of 4:2:0 video, `chroma_sample_loc_type` in the VUI.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[repr(u32)]
+pub enum ChromaSampleLocation {
+    /// Horizontally co-sited with the left luma sample and vertically in between. Used by
+    /// BT.709 content.
+    Left = 0,
+    Center = 1,
+    /// Co-sited with the top-left luma sample. Used by BT.2020 content.
+    TopLeft = 2,
+    Top = 3,
+    BottomLeft = 4,
+    Bottom = 5,
+}
+
+// The VUI fields are plain integers before 12.0
+#[cfg(feature = "v12_0")]
+impl ColorPrimaries {
+    pub(crate) fn into_nvenc(self) -> crate::sys::NV_ENC_VUI_COLOR_PRIMARIES {
+        use crate::sys::NV_ENC_VUI_COLOR_PRIMARIES;
+        match self {
+            ColorPrimaries::Bt709 => NV_ENC_VUI_COLOR_PRIMARIES::NV_ENC_VUI_COLOR_PRIMARIES_BT709,
+            ColorPrimaries::Bt2020 => NV_ENC_VUI_COLOR_PRIMARIES::NV_ENC_VUI_COLOR_PRIMARIES_BT2020,
+        }
+    }
+}
+
+#[cfg(feature = "v12_0")]
+impl TransferCharacteristics {
+    pub(crate) fn into_nvenc(self) -> crate::sys::NV_ENC_VUI_TRANSFER_CHARACTERISTIC {
+        use crate::sys::NV_ENC_VUI_TRANSFER_CHARACTERISTIC;
+        match self {
+            TransferCharacteristics::Bt709 => {
+                NV_ENC_VUI_TRANSFER_CHARACTERISTIC::NV_ENC_VUI_TRANSFER_CHARACTERISTIC_BT709
+            }
+            TransferCharacteristics::Srgb => {
+                NV_ENC_VUI_TRANSFER_CHARACTERISTIC::NV_ENC_VUI_TRANSFER_CHARACTERISTIC_SRGB
+            }
+            TransferCharacteristics::Bt2020_10Bit => {
+                NV_ENC_VUI_TRANSFER_CHARACTERISTIC::NV_ENC_VUI_TRANSFER_CHARACTERISTIC_BT2020_10
+            }
+            TransferCharacteristics::Pq => {
+                NV_ENC_VUI_TRANSFER_CHARACTERISTIC::NV_ENC_VUI_TRANSFER_CHARACTERISTIC_SMPTE2084
+            }
+            TransferCharacteristics::Hlg => {
+                NV_ENC_VUI_TRANSFER_CHARACTERISTIC::NV_ENC_VUI_TRANSFER_CHARACTERISTIC_ARIB_STD_B67
+            }
+        }
+    }
+}
+
+#[cfg(feature = "v12_0")]
+impl MatrixCoefficients {
+    pub(crate) fn into_nvenc(self) -> crate::sys::NV_ENC_VUI_MATRIX_COEFFS {
+        use crate::sys::NV_ENC_VUI_MATRIX_COEFFS;
+        match self {
+            MatrixCoefficients::Identity => NV_ENC_VUI_MATRIX_COEFFS::NV_ENC_VUI_MATRIX_COEFFS_RGB,
+            MatrixCoefficients::Bt709 => NV_ENC_VUI_MATRIX_COEFFS::NV_ENC_VUI_MATRIX_COEFFS_BT709,
+            MatrixCoefficients::Bt2020NonConstant => {
+                NV_ENC_VUI_MATRIX_COEFFS::NV_ENC_VUI_MATRIX_COEFFS_BT2020_NCL
+            }
+        }
+    }
+}
+
+/// Colour volume of the display that was used to master the content, sent in the mastering
+/// display colour volume SEI message of HDR10.
+///
+/// Chromaticity coordinates are in increments of 0.00002 and luminances in increments of
+/// 0.0001 cd/m².
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct MasteringDisplay {
+    /// (x, y) of the red primary.
+    pub red: (u16, u16),
+    /// (x, y) of the green primary.
+    pub green: (u16, u16),
+    /// (x, y) of the blue primary.
+    pub blue: (u16, u16),
+    /// (x, y) of the white point.
+    pub white_point: (u16, u16),
+    pub max_luminance: u32,
+    pub min_luminance: u32,
+}
+
+impl MasteringDisplay {
+    /// `payloadType` of the mastering display colour volume SEI message.
+    pub(crate) const SEI_PAYLOAD_TYPE: u32 = 137;
+
+    /// Body of the SEI message. The primaries are written in G, B, R order as required by HEVC.
+    pub(crate) fn sei_payload(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(24);
+        for (x, y) in [self.green, self.blue, self.red, self.white_point] {
+            payload.extend_from_slice(&x.to_be_bytes());
+            payload.extend_from_slice(&y.to_be_bytes());
+        }
+        payload.extend_from_slice(&self.max_luminance.to_be_bytes());
+        payload.extend_from_slice(&self.min_luminance.to_be_bytes());
+        payload
+    }
+}
+
+/// Light levels of the content in cd/m², sent in the content light level information SEI
+/// message of HDR10.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct ContentLightLevel {
+    /// MaxCLL, the luminance of the brightest pixel.
+    pub max_content_light_level: u16,
+    /// MaxFALL, the highest average luminance of a frame.
+    pub max_frame_average_light_level: u16,
+}
+
+impl ContentLightLevel {
+    /// `payloadType` of the content light level information SEI message.
+    pub(crate) const SEI_PAYLOAD_TYPE: u32 = 144;
+
+    pub(crate) fn sei_payload(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(4);
+        payload.extend_from_slice(&self.max_content_light_level.to_be_bytes());
+        payload.extend_from_slice(&self.max_frame_average_light_level.to_be_bytes());
+        payload
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mastering_display_sei() {
+        // Display P3 primaries with a D65 white point and 1000 cd/m² peak
+        let mastering_display = MasteringDisplay {
+            red: (34000, 16000),
+            green: (13250, 34500),
+            blue: (7500, 3000),
+            white_point: (15635, 16450),
+            max_luminance: 10_000_000,
+            min_luminance: 1,
+        };
+        assert_eq!(
+            mastering_display.sei_payload(),
+            [
+                0x33, 0xc2, 0x86, 0xc4, 0x1d, 0x4c, 0x0b, 0xb8, 0x84, 0xd0, 0x3e, 0x80, 0x3d, 0x13,
+                0x40, 0x42, 0x00, 0x98, 0x96, 0x80, 0x00, 0x00, 0x00, 0x01,
+            ]
+        );
+
+        let content_light_level = ContentLightLevel {
+            max_content_light_level: 1000,
+            max_frame_average_light_level: 400,
+        };
+        assert_eq!(content_light_level.sei_payload(), [0x03, 0xe8, 0x01, 0x90]);
+    }
+}
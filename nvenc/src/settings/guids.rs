@@ -6,6 +6,9 @@ pub const NV_ENC_CODEC_H264_GUID: crate::sys::GUID =
 pub const NV_ENC_CODEC_HEVC_GUID: crate::sys::GUID =
     guid_from_u128(0x790CDC88_4522_4d7b_9425_BDA9975F7603);
 
+pub const NV_ENC_CODEC_AV1_GUID: crate::sys::GUID =
+    guid_from_u128(0x0a352289_0aa7_4759_862d_5d15cd16d254);
+
 // Codec profiles
 
 pub const NV_ENC_CODEC_PROFILE_AUTOSELECT_GUID: crate::sys::GUID =
@@ -41,6 +44,9 @@ pub const NV_ENC_HEVC_PROFILE_MAIN10_GUID: crate::sys::GUID =
 pub const NV_ENC_HEVC_PROFILE_FREXT_GUID: crate::sys::GUID =
     guid_from_u128(0x51ec32b5_1b4c_453c_9cbd_b616bd621341);
 
+pub const NV_ENC_AV1_PROFILE_MAIN_GUID: crate::sys::GUID =
+    guid_from_u128(0x5f2a39f5_f14e_4f95_9a9e_b76d568fcf97);
+
 // Old presets
 
 pub const NV_ENC_PRESET_DEFAULT_GUID: crate::sys::GUID =
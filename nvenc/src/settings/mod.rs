@@ -1,29 +1,89 @@
+mod color;
 mod guids;
+mod sei;
 
+pub use color::{
+    ChromaSampleLocation, ColorDescription, ColorPrimaries, ContentLightLevel, MasteringDisplay,
+    MatrixCoefficients, TransferCharacteristics,
+};
 use guids::*;
+pub use sei::{SeiPayload, TimeCode};
+use std::fmt;
+
+/// Identifies a codec, profile or preset that this library does not know about. The value is in
+/// the order the GUID is written in, e.g. `0x6BC82762_4E63_4CA4_AA85_1E50F321F6BF`, and it is
+/// displayed in the registry format, e.g. `{6BC82762-4E63-4CA4-AA85-1E50F321F6BF}`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Guid(pub u128);
+
+impl Guid {
+    pub(crate) fn into_nvenc(self) -> crate::sys::GUID {
+        guid_from_u128(self.0)
+    }
+
+    pub(crate) fn from_nvenc(guid: crate::sys::GUID) -> Self {
+        Guid(
+            u128::from(guid.Data1) << 96
+                | u128::from(guid.Data2) << 80
+                | u128::from(guid.Data3) << 64
+                | u128::from(u64::from_be_bytes(guid.Data4)),
+        )
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let guid = self.0;
+        write!(
+            f,
+            "{{{:08X}-{:04X}-{:04X}-{:04X}-{:012X}}}",
+            guid >> 96,
+            guid >> 80 & 0xffff,
+            guid >> 64 & 0xffff,
+            guid >> 48 & 0xffff,
+            guid & 0xffff_ffff_ffff
+        )
+    }
+}
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum Codec {
     H264,
     Hevc,
+    /// Requires NvEnc API 12.0, i.e. the `v12_0` feature, and an Ada or newer GPU.
+    Av1,
+    /// A codec reported by the driver that this library does not know about.
+    Unknown(Guid),
 }
 
-impl Into<crate::sys::GUID> for Codec {
-    fn into(self) -> crate::sys::GUID {
+impl Codec {
+    pub(crate) fn into_nvenc(self) -> crate::sys::GUID {
         match self {
             Codec::H264 => NV_ENC_CODEC_H264_GUID,
             Codec::Hevc => NV_ENC_CODEC_HEVC_GUID,
+            Codec::Av1 => NV_ENC_CODEC_AV1_GUID,
+            Codec::Unknown(guid) => guid.into_nvenc(),
         }
     }
-}
 
-impl From<crate::sys::GUID> for Codec {
-    fn from(guid: crate::sys::GUID) -> Self {
+    pub(crate) fn from_nvenc(guid: crate::sys::GUID) -> Self {
         match guid {
             NV_ENC_CODEC_H264_GUID => Codec::H264,
             NV_ENC_CODEC_HEVC_GUID => Codec::Hevc,
-            _ => panic!("Invalid codec guid"),
+            NV_ENC_CODEC_AV1_GUID => Codec::Av1,
+            _ => Codec::Unknown(Guid::from_nvenc(guid)),
+        }
+    }
+}
+
+impl fmt::Display for Codec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Codec::H264 => f.write_str("H.264"),
+            Codec::Hevc => f.write_str("HEVC"),
+            Codec::Av1 => f.write_str("AV1"),
+            Codec::Unknown(guid) => write!(f, "Unknown {guid}"),
         }
     }
 }
@@ -42,10 +102,13 @@ pub enum CodecProfile {
     HevcMain,
     HevcMain10,
     HevcFrext,
+    Av1Main,
+    /// A profile reported by the driver that this library does not know about.
+    Unknown(Guid),
 }
 
-impl Into<crate::sys::GUID> for CodecProfile {
-    fn into(self) -> crate::sys::GUID {
+impl CodecProfile {
+    pub(crate) fn into_nvenc(self) -> crate::sys::GUID {
         match self {
             CodecProfile::Autoselect => NV_ENC_CODEC_PROFILE_AUTOSELECT_GUID,
             CodecProfile::H264Baseline => NV_ENC_H264_PROFILE_BASELINE_GUID,
@@ -58,12 +121,12 @@ impl Into<crate::sys::GUID> for CodecProfile {
             CodecProfile::HevcMain => NV_ENC_HEVC_PROFILE_MAIN_GUID,
             CodecProfile::HevcMain10 => NV_ENC_HEVC_PROFILE_MAIN10_GUID,
             CodecProfile::HevcFrext => NV_ENC_HEVC_PROFILE_FREXT_GUID,
+            CodecProfile::Av1Main => NV_ENC_AV1_PROFILE_MAIN_GUID,
+            CodecProfile::Unknown(guid) => guid.into_nvenc(),
         }
     }
-}
 
-impl From<crate::sys::GUID> for CodecProfile {
-    fn from(guid: crate::sys::GUID) -> Self {
+    pub(crate) fn from_nvenc(guid: crate::sys::GUID) -> Self {
         match guid {
             NV_ENC_CODEC_PROFILE_AUTOSELECT_GUID => CodecProfile::Autoselect,
             NV_ENC_H264_PROFILE_BASELINE_GUID => CodecProfile::H264Baseline,
@@ -76,7 +139,91 @@ impl From<crate::sys::GUID> for CodecProfile {
             NV_ENC_HEVC_PROFILE_MAIN_GUID => CodecProfile::HevcMain,
             NV_ENC_HEVC_PROFILE_MAIN10_GUID => CodecProfile::HevcMain10,
             NV_ENC_HEVC_PROFILE_FREXT_GUID => CodecProfile::HevcFrext,
-            _ => panic!("Invalid codec profile guid"),
+            NV_ENC_AV1_PROFILE_MAIN_GUID => CodecProfile::Av1Main,
+            _ => CodecProfile::Unknown(Guid::from_nvenc(guid)),
+        }
+    }
+}
+
+impl fmt::Display for CodecProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            CodecProfile::Autoselect => "Autoselect",
+            CodecProfile::H264Baseline => "H.264 Baseline",
+            CodecProfile::H264Main => "H.264 Main",
+            CodecProfile::H264High => "H.264 High",
+            CodecProfile::H264High444 => "H.264 High 4:4:4",
+            CodecProfile::H264Stereo => "H.264 Stereo",
+            CodecProfile::H264ProgressiveHigh => "H.264 Progressive High",
+            CodecProfile::H264ConstrainedHigh => "H.264 Constrained High",
+            CodecProfile::HevcMain => "HEVC Main",
+            CodecProfile::HevcMain10 => "HEVC Main 10",
+            CodecProfile::HevcFrext => "HEVC Format Range Extensions",
+            CodecProfile::Av1Main => "AV1 Main",
+            CodecProfile::Unknown(guid) => return write!(f, "Unknown {guid}"),
+        };
+        f.write_str(name)
+    }
+}
+
+/// Level of a codec, which limits the resolution, frame rate and bitrate of the bitstream.
+///
+/// The values are the ones written to the bitstream, e.g. `H264(41)` and `Hevc(123)` are both
+/// level 4.1, and `Av1(8)` is level 4.0.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Level {
+    Autoselect,
+    /// `level_idc`, which is 10 times the level. Level 1b is 9.
+    H264(u32),
+    /// `general_level_idc`, which is 30 times the level.
+    Hevc(u32),
+    /// `seq_level_idx`, where 0 is level 2.0 and every major level has 4 minor levels.
+    Av1(u32),
+    /// A level of a codec that this library does not know about.
+    Unknown(u32),
+}
+
+impl Level {
+    /// `NV_ENC_LEVEL_AUTOSELECT`
+    const AUTOSELECT: u32 = 0;
+    /// `NV_ENC_LEVEL_AV1_AUTOSELECT`, as 0 is level 2.0 for AV1
+    const AV1_AUTOSELECT: u32 = 24;
+
+    /// Interprets the `NV_ENC_LEVEL` of `codec` that NvEnc uses in configs and capabilities.
+    pub(crate) fn from_nvenc(codec: Codec, level: u32) -> Self {
+        match codec {
+            Codec::Av1 if level == Level::AV1_AUTOSELECT => Level::Autoselect,
+            Codec::Av1 => Level::Av1(level),
+            _ if level == Level::AUTOSELECT => Level::Autoselect,
+            Codec::H264 => Level::H264(level),
+            Codec::Hevc => Level::Hevc(level),
+            Codec::Unknown(_) => Level::Unknown(level),
+        }
+    }
+
+    /// The `NV_ENC_LEVEL` for `codec`. Levels of other codecs fall back to autoselect.
+    pub(crate) fn into_nvenc(self, codec: Codec) -> u32 {
+        match (self, codec) {
+            (Level::H264(level), Codec::H264)
+            | (Level::Hevc(level), Codec::Hevc)
+            | (Level::Av1(level), Codec::Av1)
+            | (Level::Unknown(level), Codec::Unknown(_)) => level,
+            (_, Codec::Av1) => Level::AV1_AUTOSELECT,
+            _ => Level::AUTOSELECT,
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Level::Autoselect => f.write_str("Autoselect"),
+            Level::H264(9) => f.write_str("H.264 Level 1b"),
+            Level::H264(level) => write!(f, "H.264 Level {}.{}", level / 10, level % 10),
+            Level::Hevc(level) => write!(f, "HEVC Level {}.{}", level / 30, level % 30 / 3),
+            Level::Av1(level) => write!(f, "AV1 Level {}.{}", 2 + level / 4, level % 4),
+            Level::Unknown(level) => write!(f, "Unknown level {level}"),
         }
     }
 }
@@ -101,10 +248,12 @@ pub enum EncodePreset {
     P5,
     P6,
     P7,
+    /// A preset reported by the driver that this library does not know about.
+    Unknown(Guid),
 }
 
-impl Into<crate::sys::GUID> for EncodePreset {
-    fn into(self) -> crate::sys::GUID {
+impl EncodePreset {
+    pub(crate) fn into_nvenc(self) -> crate::sys::GUID {
         match self {
             EncodePreset::DefaultPreset => NV_ENC_PRESET_DEFAULT_GUID,
             EncodePreset::Hp => NV_ENC_PRESET_HP_GUID,
@@ -123,12 +272,11 @@ impl Into<crate::sys::GUID> for EncodePreset {
             EncodePreset::P5 => NV_ENC_PRESET_P5_GUID,
             EncodePreset::P6 => NV_ENC_PRESET_P6_GUID,
             EncodePreset::P7 => NV_ENC_PRESET_P7_GUID,
+            EncodePreset::Unknown(guid) => guid.into_nvenc(),
         }
     }
-}
 
-impl From<crate::sys::GUID> for EncodePreset {
-    fn from(guid: crate::sys::GUID) -> Self {
+    pub(crate) fn from_nvenc(guid: crate::sys::GUID) -> Self {
         match guid {
             NV_ENC_PRESET_DEFAULT_GUID => EncodePreset::DefaultPreset,
             NV_ENC_PRESET_HP_GUID => EncodePreset::Hp,
@@ -147,11 +295,37 @@ impl From<crate::sys::GUID> for EncodePreset {
             NV_ENC_PRESET_P5_GUID => EncodePreset::P5,
             NV_ENC_PRESET_P6_GUID => EncodePreset::P6,
             NV_ENC_PRESET_P7_GUID => EncodePreset::P7,
-            _ => panic!("Invalid encoder preset"),
+            _ => EncodePreset::Unknown(Guid::from_nvenc(guid)),
         }
     }
 }
 
+impl fmt::Display for EncodePreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            EncodePreset::DefaultPreset => "Default",
+            EncodePreset::Hp => "HP",
+            EncodePreset::Hq => "HQ",
+            EncodePreset::Bd => "BD",
+            EncodePreset::LowLatencyDefault => "Low latency default",
+            EncodePreset::LowLatencyHq => "Low latency HQ",
+            EncodePreset::LowLatencyHp => "Low latency HP",
+            EncodePreset::LosslessDefault => "Lossless default",
+            EncodePreset::LosslessHp => "Lossless HP",
+            EncodePreset::Streaming => "Streaming",
+            EncodePreset::P1 => "P1",
+            EncodePreset::P2 => "P2",
+            EncodePreset::P3 => "P3",
+            EncodePreset::P4 => "P4",
+            EncodePreset::P5 => "P5",
+            EncodePreset::P6 => "P6",
+            EncodePreset::P7 => "P7",
+            EncodePreset::Unknown(guid) => return write!(f, "Unknown {guid}"),
+        };
+        f.write_str(name)
+    }
+}
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[non_exhaustive]
 pub enum TuningInfo {
@@ -162,8 +336,8 @@ pub enum TuningInfo {
     Lossless,
 }
 
-impl Into<crate::sys::NV_ENC_TUNING_INFO> for TuningInfo {
-    fn into(self) -> crate::sys::NV_ENC_TUNING_INFO {
+impl TuningInfo {
+    pub(crate) fn into_nvenc(self) -> crate::sys::NV_ENC_TUNING_INFO {
         use crate::sys::NV_ENC_TUNING_INFO;
         match self {
             TuningInfo::Undefined => NV_ENC_TUNING_INFO::NV_ENC_TUNING_INFO_UNDEFINED,
@@ -175,17 +349,15 @@ impl Into<crate::sys::NV_ENC_TUNING_INFO> for TuningInfo {
     }
 }
 
-impl From<crate::sys::NV_ENC_TUNING_INFO> for TuningInfo {
-    fn from(tuning_info: crate::sys::NV_ENC_TUNING_INFO) -> Self {
-        use crate::sys::NV_ENC_TUNING_INFO;
-        match tuning_info {
-            NV_ENC_TUNING_INFO::NV_ENC_TUNING_INFO_UNDEFINED => TuningInfo::Undefined,
-            NV_ENC_TUNING_INFO::NV_ENC_TUNING_INFO_HIGH_QUALITY => TuningInfo::HighQuality,
-            NV_ENC_TUNING_INFO::NV_ENC_TUNING_INFO_LOW_LATENCY => TuningInfo::LowLatency,
-            NV_ENC_TUNING_INFO::NV_ENC_TUNING_INFO_ULTRA_LOW_LATENCY => TuningInfo::UltraLowLatency,
-            NV_ENC_TUNING_INFO::NV_ENC_TUNING_INFO_LOSSLESS => TuningInfo::Lossless,
-            _ => panic!("Invalid tuning info"),
-        }
+impl fmt::Display for TuningInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TuningInfo::Undefined => "Undefined",
+            TuningInfo::HighQuality => "High quality",
+            TuningInfo::LowLatency => "Low latency",
+            TuningInfo::UltraLowLatency => "Ultra-low latency",
+            TuningInfo::Lossless => "Lossless",
+        })
     }
 }
 
@@ -197,8 +369,8 @@ pub enum MultiPassSetting {
     FullResolution,
 }
 
-impl Into<crate::sys::NV_ENC_MULTI_PASS> for MultiPassSetting {
-    fn into(self) -> crate::sys::NV_ENC_MULTI_PASS {
+impl MultiPassSetting {
+    pub(crate) fn into_nvenc(self) -> crate::sys::NV_ENC_MULTI_PASS {
         use crate::sys::NV_ENC_MULTI_PASS;
         match self {
             MultiPassSetting::Disabled => NV_ENC_MULTI_PASS::NV_ENC_MULTI_PASS_DISABLED,
@@ -210,16 +382,387 @@ impl Into<crate::sys::NV_ENC_MULTI_PASS> for MultiPassSetting {
     }
 }
 
-impl From<crate::sys::NV_ENC_MULTI_PASS> for MultiPassSetting {
-    fn from(multi_pass: crate::sys::NV_ENC_MULTI_PASS) -> Self {
-        use crate::sys::NV_ENC_MULTI_PASS;
-        match multi_pass {
-            NV_ENC_MULTI_PASS::NV_ENC_MULTI_PASS_DISABLED => MultiPassSetting::Disabled,
-            NV_ENC_MULTI_PASS::NV_ENC_TWO_PASS_QUARTER_RESOLUTION => {
-                MultiPassSetting::QuarterResolution
+impl fmt::Display for MultiPassSetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            MultiPassSetting::Disabled => "Disabled",
+            MultiPassSetting::QuarterResolution => "Two passes at quarter resolution",
+            MultiPassSetting::FullResolution => "Two passes at full resolution",
+        })
+    }
+}
+
+/// Quantization parameters for each frame type.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Qp {
+    pub i: u32,
+    pub p: u32,
+    pub b: u32,
+}
+
+impl Qp {
+    pub(crate) fn into_nvenc(self) -> crate::sys::NV_ENC_QP {
+        crate::sys::NV_ENC_QP {
+            qpInterP: self.p,
+            qpInterB: self.b,
+            qpIntra: self.i,
+        }
+    }
+}
+
+/// Rate control mode and its targets. Bitrates are in bits per second.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RateControl {
+    /// Constant QP.
+    ConstQp(Qp),
+    /// Variable bitrate.
+    Vbr { average: u32, max: u32 },
+    /// Constant bitrate.
+    Cbr { bitrate: u32 },
+    /// Variable bitrate that targets a constant quality level `cq` (1-51, lower is better)
+    /// without going over `max`. A `cq` of 0 lets the encoder pick the level.
+    TargetQuality { cq: u8, max: u32 },
+}
+
+impl RateControl {
+    pub(crate) fn into_nvenc(self) -> crate::sys::NV_ENC_PARAMS_RC_MODE {
+        use crate::sys::NV_ENC_PARAMS_RC_MODE;
+        match self {
+            RateControl::ConstQp(_) => NV_ENC_PARAMS_RC_MODE::NV_ENC_PARAMS_RC_CONSTQP,
+            RateControl::Vbr { .. } | RateControl::TargetQuality { .. } => {
+                NV_ENC_PARAMS_RC_MODE::NV_ENC_PARAMS_RC_VBR
+            }
+            RateControl::Cbr { .. } => NV_ENC_PARAMS_RC_MODE::NV_ENC_PARAMS_RC_CBR,
+        }
+    }
+}
+
+/// Which B-frames can be used as references by other frames.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BFrameRefMode {
+    Disabled,
+    /// Every B-frame.
+    Each,
+    /// Only the middle B-frame of a group of consecutive B-frames.
+    Middle,
+}
+
+impl BFrameRefMode {
+    pub(crate) fn into_nvenc(self) -> crate::sys::NV_ENC_BFRAME_REF_MODE {
+        use crate::sys::NV_ENC_BFRAME_REF_MODE;
+        match self {
+            BFrameRefMode::Disabled => NV_ENC_BFRAME_REF_MODE::NV_ENC_BFRAME_REF_MODE_DISABLED,
+            BFrameRefMode::Each => NV_ENC_BFRAME_REF_MODE::NV_ENC_BFRAME_REF_MODE_EACH,
+            BFrameRefMode::Middle => NV_ENC_BFRAME_REF_MODE::NV_ENC_BFRAME_REF_MODE_MIDDLE,
+        }
+    }
+}
+
+/// How a frame is split into slices. Sizes are in macroblocks for H.264 and CTUs for HEVC.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SliceMode {
+    /// Slices of the given number of macroblocks/CTUs.
+    Blocks(u32),
+    /// Slices of at most the given number of bytes, e.g. to fit each NAL unit into a packet.
+    MaxBytes(u32),
+    /// Slices of the given number of macroblock/CTU rows.
+    Rows(u32),
+    /// The given number of slices, sized by the encoder.
+    Count(u32),
+}
+
+impl SliceMode {
+    /// `sliceMode` and `sliceModeData` of the codec config.
+    pub(crate) fn mode_and_data(self) -> (u32, u32) {
+        match self {
+            SliceMode::Blocks(blocks) => (0, blocks),
+            SliceMode::MaxBytes(bytes) => (1, bytes),
+            SliceMode::Rows(rows) => (2, rows),
+            SliceMode::Count(count) => (3, count),
+        }
+    }
+}
+
+/// How the QP map passed with each frame is interpreted.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum QpMapMode {
+    /// Offsets added to the QP chosen by the rate control.
+    Delta,
+    /// Emphasis levels from 0 to 5 that the encoder turns into QP offsets. Only H.264.
+    Emphasis,
+}
+
+impl QpMapMode {
+    pub(crate) fn into_nvenc(self) -> crate::sys::NV_ENC_QP_MAP_MODE {
+        use crate::sys::NV_ENC_QP_MAP_MODE;
+        match self {
+            QpMapMode::Delta => NV_ENC_QP_MAP_MODE::NV_ENC_QP_MAP_DELTA,
+            QpMapMode::Emphasis => NV_ENC_QP_MAP_MODE::NV_ENC_QP_MAP_EMPHASIS,
+        }
+    }
+}
+
+/// Picture type that a frame is forced to be encoded as.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ForcedPictureType {
+    /// Predicted from earlier frames. Requires picture type decision to be disabled.
+    P,
+    /// Intra-coded frame that later frames can still predict across.
+    Intra,
+    /// Intra-coded frame that starts a new coded video sequence.
+    Idr,
+    /// Frame that repeats the previous one without coding anything. Requires picture type
+    /// decision to be disabled.
+    Skipped,
+}
+
+impl ForcedPictureType {
+    pub(crate) fn into_nvenc(self) -> crate::sys::NV_ENC_PIC_TYPE {
+        use crate::sys::NV_ENC_PIC_TYPE;
+        match self {
+            ForcedPictureType::P => NV_ENC_PIC_TYPE::NV_ENC_PIC_TYPE_P,
+            ForcedPictureType::Intra => NV_ENC_PIC_TYPE::NV_ENC_PIC_TYPE_I,
+            ForcedPictureType::Idr => NV_ENC_PIC_TYPE::NV_ENC_PIC_TYPE_IDR,
+            ForcedPictureType::Skipped => NV_ENC_PIC_TYPE::NV_ENC_PIC_TYPE_SKIPPED,
+        }
+    }
+}
+
+/// Type of an encoded picture.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum PictureType {
+    P,
+    B,
+    I,
+    Idr,
+    /// B-frame with only intra-coded blocks.
+    Bi,
+    Skipped,
+    /// First picture of an intra refresh wave.
+    IntraRefresh,
+    /// P-frame that is not used as a reference.
+    NonReferenceP,
+    Unknown,
+}
+
+impl PictureType {
+    /// Takes a reference since the sys enums are neither `Copy` nor exhaustive.
+    pub(crate) fn from_nvenc(picture_type: &crate::sys::NV_ENC_PIC_TYPE) -> Self {
+        use crate::sys::NV_ENC_PIC_TYPE;
+        match picture_type {
+            NV_ENC_PIC_TYPE::NV_ENC_PIC_TYPE_P => PictureType::P,
+            NV_ENC_PIC_TYPE::NV_ENC_PIC_TYPE_B => PictureType::B,
+            NV_ENC_PIC_TYPE::NV_ENC_PIC_TYPE_I => PictureType::I,
+            NV_ENC_PIC_TYPE::NV_ENC_PIC_TYPE_IDR => PictureType::Idr,
+            NV_ENC_PIC_TYPE::NV_ENC_PIC_TYPE_BI => PictureType::Bi,
+            NV_ENC_PIC_TYPE::NV_ENC_PIC_TYPE_SKIPPED => PictureType::Skipped,
+            NV_ENC_PIC_TYPE::NV_ENC_PIC_TYPE_INTRA_REFRESH => PictureType::IntraRefresh,
+            NV_ENC_PIC_TYPE::NV_ENC_PIC_TYPE_NONREF_P => PictureType::NonReferenceP,
+            _ => PictureType::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for PictureType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            PictureType::P => "P",
+            PictureType::B => "B",
+            PictureType::I => "I",
+            PictureType::Idr => "IDR",
+            PictureType::Bi => "BI",
+            PictureType::Skipped => "Skipped",
+            PictureType::IntraRefresh => "Intra Refresh",
+            PictureType::NonReferenceP => "Non-reference P",
+            PictureType::Unknown => "Unknown",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Whether a frame is progressive or made of two interlaced fields.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+pub enum PictureStructure {
+    #[default]
+    Frame,
+    /// Two fields, the top one first.
+    FieldTopBottom,
+    /// Two fields, the bottom one first.
+    FieldBottomTop,
+}
+
+impl PictureStructure {
+    pub(crate) fn into_nvenc(self) -> crate::sys::NV_ENC_PIC_STRUCT {
+        use crate::sys::NV_ENC_PIC_STRUCT;
+        match self {
+            PictureStructure::Frame => NV_ENC_PIC_STRUCT::NV_ENC_PIC_STRUCT_FRAME,
+            PictureStructure::FieldTopBottom => {
+                NV_ENC_PIC_STRUCT::NV_ENC_PIC_STRUCT_FIELD_TOP_BOTTOM
+            }
+            PictureStructure::FieldBottomTop => {
+                NV_ENC_PIC_STRUCT::NV_ENC_PIC_STRUCT_FIELD_BOTTOM_TOP
             }
-            NV_ENC_MULTI_PASS::NV_ENC_TWO_PASS_FULL_RESOLUTION => MultiPassSetting::FullResolution,
-            _ => panic!("Invalid multi-pass setting"),
         }
     }
 }
+
+/// Pixel layout of frames stored in CUDA device memory or in host memory.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BufferFormat {
+    /// Semi-planar YUV 4:2:0, a Y plane followed by an interleaved UV plane.
+    Nv12,
+    /// Planar YUV 4:2:0, a Y plane followed by V and U planes with half the pitch.
+    Yv12,
+    /// Planar YUV 4:2:0, a Y plane followed by U and V planes with half the pitch.
+    Iyuv,
+    /// Planar YUV 4:4:4.
+    Yuv444,
+    /// Semi-planar YUV 4:2:0 with 2 bytes per sample where the 10 most significant bits hold the
+    /// data (P010).
+    Yuv420_10Bit,
+    /// Planar YUV 4:4:4 with 2 bytes per sample where the 10 most significant bits hold the data.
+    Yuv444_10Bit,
+    /// Packed 8-bit ARGB, stored as B, G, R, A in memory.
+    Argb,
+    /// Packed 10-bit ARGB, stored as a 32-bit word with A in the 2 most significant bits.
+    Argb10,
+    /// Packed 8-bit AYUV.
+    Ayuv,
+    /// Packed 8-bit ABGR, stored as R, G, B, A in memory.
+    Abgr,
+    /// Packed 10-bit ABGR, stored as a 32-bit word with A in the 2 most significant bits.
+    Abgr10,
+}
+
+impl BufferFormat {
+    pub(crate) fn into_nvenc(self) -> crate::sys::NV_ENC_BUFFER_FORMAT {
+        use crate::sys::NV_ENC_BUFFER_FORMAT;
+        match self {
+            BufferFormat::Nv12 => NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_NV12,
+            BufferFormat::Yv12 => NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_YV12,
+            BufferFormat::Iyuv => NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_IYUV,
+            BufferFormat::Yuv444 => NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_YUV444,
+            BufferFormat::Yuv420_10Bit => NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_YUV420_10BIT,
+            BufferFormat::Yuv444_10Bit => NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_YUV444_10BIT,
+            BufferFormat::Argb => NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_ARGB,
+            BufferFormat::Argb10 => NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_ARGB10,
+            BufferFormat::Ayuv => NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_AYUV,
+            BufferFormat::Abgr => NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_ABGR,
+            BufferFormat::Abgr10 => NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_ABGR10,
+        }
+    }
+
+    pub(crate) fn try_from_nvenc(format: &crate::sys::NV_ENC_BUFFER_FORMAT) -> crate::Result<Self> {
+        use crate::sys::NV_ENC_BUFFER_FORMAT;
+        let format = match format {
+            NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_NV12 => BufferFormat::Nv12,
+            NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_YV12 => BufferFormat::Yv12,
+            NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_IYUV => BufferFormat::Iyuv,
+            NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_YUV444 => BufferFormat::Yuv444,
+            NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_YUV420_10BIT => BufferFormat::Yuv420_10Bit,
+            NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_YUV444_10BIT => BufferFormat::Yuv444_10Bit,
+            NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_ARGB => BufferFormat::Argb,
+            NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_ARGB10 => BufferFormat::Argb10,
+            NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_AYUV => BufferFormat::Ayuv,
+            NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_ABGR => BufferFormat::Abgr,
+            NV_ENC_BUFFER_FORMAT::NV_ENC_BUFFER_FORMAT_ABGR10 => BufferFormat::Abgr10,
+            // `UNDEFINED`, and `U8` which is only used for motion estimation
+            _ => return Err(crate::NvEncError::UnknownValue),
+        };
+        Ok(format)
+    }
+}
+
+impl fmt::Display for BufferFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            BufferFormat::Nv12 => "NV12",
+            BufferFormat::Yv12 => "YV12",
+            BufferFormat::Iyuv => "IYUV",
+            BufferFormat::Yuv444 => "YUV 4:4:4",
+            BufferFormat::Yuv420_10Bit => "P010",
+            BufferFormat::Yuv444_10Bit => "YUV 4:4:4 10-bit",
+            BufferFormat::Argb => "ARGB",
+            BufferFormat::Argb10 => "ARGB10",
+            BufferFormat::Ayuv => "AYUV",
+            BufferFormat::Abgr => "ABGR",
+            BufferFormat::Abgr10 => "ABGR10",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How a thread waits for the other side of the encoder: `EncoderInput` for a free buffer and
+/// `EncoderOutput` for the next frame.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum WaitStrategy {
+    /// Yield the thread in a loop. Reacts the fastest, but keeps a core busy while waiting.
+    Spin,
+    /// Yield the thread `spins` times, then block until the other side wakes it up.
+    SpinThenBlock { spins: u32 },
+    /// Block until the other side wakes the thread up. Uses no CPU while waiting, but waking up
+    /// adds latency.
+    Block,
+}
+
+impl Default for WaitStrategy {
+    fn default() -> Self {
+        WaitStrategy::SpinThenBlock { spins: 100 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_guids_round_trip() {
+        let guid = Guid(0x01234567_89ab_cdef_0123_456789abcdef);
+        assert_eq!(Guid::from_nvenc(guid.into_nvenc()), guid);
+
+        let codec = Codec::from_nvenc(guid.into_nvenc());
+        assert_eq!(codec, Codec::Unknown(guid));
+        assert_eq!(codec.into_nvenc(), guid.into_nvenc());
+        assert_eq!(
+            codec.to_string(),
+            "Unknown {01234567-89AB-CDEF-0123-456789ABCDEF}"
+        );
+
+        assert_eq!(Codec::from_nvenc(NV_ENC_CODEC_HEVC_GUID), Codec::Hevc);
+        assert_eq!(
+            CodecProfile::from_nvenc(guid.into_nvenc()),
+            CodecProfile::Unknown(guid)
+        );
+        assert_eq!(
+            EncodePreset::from_nvenc(guid.into_nvenc()),
+            EncodePreset::Unknown(guid)
+        );
+    }
+
+    #[test]
+    fn levels_round_trip() {
+        for (codec, level, name) in [
+            (Codec::H264, Level::H264(9), "H.264 Level 1b"),
+            (Codec::H264, Level::H264(41), "H.264 Level 4.1"),
+            (Codec::Hevc, Level::Hevc(123), "HEVC Level 4.1"),
+            (Codec::Av1, Level::Av1(0), "AV1 Level 2.0"),
+            (Codec::Av1, Level::Av1(23), "AV1 Level 7.3"),
+        ] {
+            assert_eq!(Level::from_nvenc(codec, level.into_nvenc(codec)), level);
+            assert_eq!(level.to_string(), name);
+        }
+
+        assert_eq!(Level::from_nvenc(Codec::H264, 0), Level::Autoselect);
+        assert_eq!(Level::from_nvenc(Codec::Av1, 24), Level::Autoselect);
+        assert_eq!(Level::Autoselect.into_nvenc(Codec::Av1), 24);
+        // Levels of other codecs are not passed on
+        assert_eq!(Level::H264(41).into_nvenc(Codec::Hevc), 0);
+    }
+}
@@ -0,0 +1,167 @@
+//! Messages that are inserted into the bitstream of a frame: SEI messages for H.264 and HEVC and
+//! metadata OBUs for AV1.
+
+use crate::{NvEncError, Result};
+
+/// A single SEI message or metadata OBU.
+///
+/// Any message can be built from its payload type and body, the constructors cover the common
+/// ones.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SeiPayload {
+    /// `payloadType` of the SEI message, or `metadata_type` of the metadata OBU for AV1.
+    pub payload_type: u32,
+    /// Body of the message without emulation prevention bytes, which the encoder inserts.
+    pub data: Vec<u8>,
+}
+
+impl SeiPayload {
+    /// `payloadType` of the registered user data SEI message, which is also `metadata_type` of
+    /// the ITU-T T.35 metadata OBU of AV1.
+    const ITU_T_T35: u32 = 4;
+    /// `payloadType` of the unregistered user data SEI message. The metadata OBU of AV1 with this
+    /// `metadata_type` is a timecode instead.
+    pub(crate) const USER_DATA_UNREGISTERED: u32 = 5;
+    /// `payloadType` of the time code SEI message of HEVC.
+    pub(crate) const TIME_CODE: u32 = 136;
+
+    /// User data registered by ITU-T T.35, e.g. CEA-708 closed captions. `data` starts with the
+    /// country code. Works for every codec.
+    pub fn itu_t_t35(data: Vec<u8>) -> Self {
+        SeiPayload {
+            payload_type: SeiPayload::ITU_T_T35,
+            data,
+        }
+    }
+
+    /// User data identified by a UUID. Only H.264 and HEVC, frames with it are rejected with
+    /// `UnsupportedSetting` for AV1.
+    pub fn user_data_unregistered(uuid: [u8; 16], data: &[u8]) -> Self {
+        let mut payload = Vec::with_capacity(uuid.len() + data.len());
+        payload.extend_from_slice(&uuid);
+        payload.extend_from_slice(data);
+        SeiPayload {
+            payload_type: SeiPayload::USER_DATA_UNREGISTERED,
+            data: payload,
+        }
+    }
+
+    /// Time code of the frame. Only HEVC, frames with it are rejected with `UnsupportedSetting`
+    /// for the other codecs. Returns `UnsupportedSetting` if a field of `time_code` is out of
+    /// range.
+    pub fn time_code(time_code: TimeCode) -> Result<Self> {
+        let TimeCode {
+            hours,
+            minutes,
+            seconds,
+            frames,
+            drop_frame,
+        } = time_code;
+        if hours > 23 || minutes > 59 || seconds > 59 || frames > 511 {
+            return Err(NvEncError::UnsupportedSetting);
+        }
+
+        let mut writer = BitWriter::default();
+        // num_clock_ts, clock_timestamp_flag, units_field_based_flag
+        writer.write(1, 2);
+        writer.write(1, 1);
+        writer.write(0, 1);
+        // counting_type 4 drops the first two frame numbers of each minute except every tenth
+        writer.write(if drop_frame { 4 } else { 0 }, 5);
+        // full_timestamp_flag, discontinuity_flag, cnt_dropped_flag
+        writer.write(1, 1);
+        writer.write(0, 1);
+        writer.write(0, 1);
+        writer.write(u32::from(frames), 9);
+        writer.write(u32::from(seconds), 6);
+        writer.write(u32::from(minutes), 6);
+        writer.write(u32::from(hours), 5);
+        // time_offset_length
+        writer.write(0, 5);
+        Ok(SeiPayload {
+            payload_type: SeiPayload::TIME_CODE,
+            data: writer.finish(),
+        })
+    }
+}
+
+/// A SMPTE time code, sent with `SeiPayload::time_code`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct TimeCode {
+    /// 0-23.
+    pub hours: u8,
+    /// 0-59.
+    pub minutes: u8,
+    /// 0-59.
+    pub seconds: u8,
+    /// Number of the frame within the second, 0-511.
+    pub frames: u16,
+    /// Whether the frames are counted with drop-frame time code, e.g. for 29.97 fps.
+    pub drop_frame: bool,
+}
+
+/// Writes the fields of an SEI message most significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    /// Number of bits written to the last byte, 8 if it is full.
+    used_bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u32, len: u32) {
+        for bit in (0..len).rev() {
+            if self.bytes.is_empty() || self.used_bits == 8 {
+                self.bytes.push(0);
+                self.used_bits = 0;
+            }
+            let last = self.bytes.len() - 1;
+            self.bytes[last] |= (((value >> bit) & 1) as u8) << (7 - self.used_bits);
+            self.used_bits += 1;
+        }
+    }
+
+    /// Ends the message with a one bit and zero bits up to the byte boundary unless it already
+    /// ends on one.
+    fn finish(mut self) -> Vec<u8> {
+        if self.used_bits != 8 {
+            self.write(1, 1);
+            self.write(0, 8 - self.used_bits);
+        }
+        self.bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_code_sei() {
+        let time_code = TimeCode {
+            hours: 10,
+            minutes: 20,
+            seconds: 30,
+            frames: 15,
+            drop_frame: false,
+        };
+        let sei = SeiPayload::time_code(time_code).unwrap();
+        assert_eq!(sei.payload_type, 136);
+        assert_eq!(sei.data, [0x60, 0x40, 0x7b, 0xca, 0x28, 0x10]);
+
+        let drop_frame = TimeCode {
+            drop_frame: true,
+            ..time_code
+        };
+        assert_eq!(
+            SeiPayload::time_code(drop_frame).unwrap().data,
+            [0x62, 0x40, 0x7b, 0xca, 0x28, 0x10]
+        );
+
+        let out_of_range = TimeCode {
+            minutes: 60,
+            ..time_code
+        };
+        assert!(SeiPayload::time_code(out_of_range).is_err());
+    }
+}
@@ -1 +1,4 @@
-pub use nvenc_sys::v11_1::*;
+#[cfg(not(feature = "v12_0"))]
+pub use nvenc_sys::v11_1::*;
+#[cfg(feature = "v12_0")]
+pub use nvenc_sys::v12_0::*;